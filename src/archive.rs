@@ -0,0 +1,267 @@
+use flate2::read::GzDecoder;
+use rayon::prelude::*;
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::scanner::ScanOptions;
+use crate::tree::TreeNode;
+
+/// 支持展开的压缩包格式
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+/// 根据文件名识别压缩包格式
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+        Some(ArchiveKind::TarZst)
+    } else {
+        None
+    }
+}
+
+/// 是否为可以展开的压缩包
+pub fn is_archive(path: &Path) -> bool {
+    archive_kind(path).is_some()
+}
+
+/// 压缩包内的路径只保留普通的路径组成部分，忽略 `..` 和绝对路径前缀
+fn normalize(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 读取压缩包中的文件列表，返回（包内路径，解压后大小）
+fn read_entries(path: &Path) -> io::Result<Vec<(Vec<String>, u64)>> {
+    let Some(kind) = archive_kind(path) else {
+        return Ok(Vec::new());
+    };
+    let file = BufReader::new(File::open(path)?);
+
+    let mut entries = Vec::new();
+    if let ArchiveKind::Zip = kind {
+        let mut zip = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+        for idx in 0..zip.len() {
+            // 只读取目录信息，不解压内容
+            let entry = zip.by_index_raw(idx).map_err(io::Error::other)?;
+            if entry.is_dir() {
+                continue;
+            }
+            if let Some(name) = entry.enclosed_name() {
+                entries.push((normalize(&name), entry.size()));
+            }
+        }
+    } else {
+        let reader: Box<dyn Read> = match kind {
+            ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+            ArchiveKind::TarZst => Box::new(zstd::Decoder::with_buffer(file)?),
+            _ => Box::new(file),
+        };
+        let mut tar = tar::Archive::new(reader);
+        for entry in tar.entries()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            entries.push((normalize(&entry.path()?), entry.size()));
+        }
+    }
+    entries.retain(|(components, _)| !components.is_empty());
+    Ok(entries)
+}
+
+/// 将一个文件插入虚拟目录，缺失的中间目录会被创建
+fn insert_entry(node: &mut TreeNode, components: &[String], size: u64) {
+    let TreeNode::Directory { path, files, dirs, .. } = node else {
+        return;
+    };
+    let child_path = path.join(&components[0]);
+    if components.len() == 1 {
+        files.push(TreeNode::new_file(child_path, size));
+        return;
+    }
+    let idx = match dirs.iter().position(|d| d.path() == child_path) {
+        Some(idx) => idx,
+        None => {
+            dirs.push(TreeNode::new_directory(child_path));
+            dirs.len() - 1
+        }
+    };
+    insert_entry(&mut dirs[idx], &components[1..], size);
+}
+
+/// 子目录按名称排序，并自底向上计算统计
+fn finish(node: &mut TreeNode) {
+    if let TreeNode::Directory { files, dirs, .. } = node {
+        files.sort_by(|a, b| a.name().cmp(b.name()));
+        dirs.sort_by(|a, b| a.name().cmp(b.name()));
+        for dir in dirs.iter_mut() {
+            finish(dir);
+        }
+    }
+    node.refresh_totals();
+}
+
+/// 将压缩包展开为深度为 `depth` 的虚拟目录
+///
+/// 包内的条目与磁盘上的目录一样跳过隐藏和被忽略的条目、遵守深度限制，并应用相同的过滤条件。
+pub fn expand(path: &Path, size: u64, options: &ScanOptions, depth: usize) -> io::Result<TreeNode> {
+    let mut node = TreeNode::new_directory(path.to_path_buf());
+    if let TreeNode::Directory { archive_size, .. } = &mut node {
+        *archive_size = Some(size);
+    }
+
+    for (components, entry_size) in read_entries(path)? {
+        // 文件所在的虚拟目录的深度
+        if options.beyond_depth(depth + components.len() - 1) {
+            continue;
+        }
+        if components.iter().any(|name| options.skips(name)) {
+            continue;
+        }
+        let entry_path: PathBuf = path.join(components.iter().collect::<PathBuf>());
+        if options.filter.matches(&entry_path, entry_size) {
+            insert_entry(&mut node, &components, entry_size);
+        }
+    }
+
+    finish(&mut node);
+    Ok(node)
+}
+
+/// 并行展开文件列表中的压缩包，返回其余的普通文件和展开后的虚拟目录
+///
+/// `depth` 是展开后的虚拟目录的深度，超出深度限制时压缩包按普通文件显示。
+pub fn expand_archives(
+    files: Vec<(PathBuf, u64)>,
+    options: &ScanOptions,
+    depth: usize,
+) -> (Vec<(PathBuf, u64)>, Vec<TreeNode>) {
+    let filter = &options.filter;
+    let expand_allowed = !options.beyond_depth(depth);
+    let expanded: Vec<Result<TreeNode, (PathBuf, u64)>> = files
+        .into_par_iter()
+        .map(|(path, size)| {
+            if !expand_allowed || !is_archive(&path) {
+                return Err((path, size));
+            }
+            expand(&path, size, options, depth).map_err(|err| {
+                options.report_error(&path, &err);
+                (path, size)
+            })
+        })
+        .collect();
+
+    let mut plain = Vec::new();
+    let mut archives = Vec::new();
+    for result in expanded {
+        match result {
+            // 包内的文件都被过滤掉时不显示
+            Ok(node) => {
+                if matches!(node, TreeNode::Directory { total_files, .. } if total_files > 0) {
+                    archives.push(node);
+                }
+            }
+            Err((path, size)) => {
+                // 无法展开或超出深度限制的压缩包按普通文件过滤
+                if !is_archive(&path) || filter.matches(&path, size) {
+                    plain.push((path, size));
+                }
+            }
+        }
+    }
+    (plain, archives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在临时目录中创建包含指定文件的 tar 包
+    fn write_tar(name: &str, entries: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ntree-archive-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        for (entry, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, entry, *data).unwrap();
+        }
+        builder.finish().unwrap();
+        path
+    }
+
+    fn names(node: &TreeNode) -> Vec<String> {
+        let mut out = Vec::new();
+        if let TreeNode::Directory { files, dirs, .. } = node {
+            for child in files.iter().chain(dirs.iter()) {
+                out.push(child.name().to_string());
+                out.extend(names(child).into_iter().map(|name| format!("{}/{}", child.name(), name)));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn expand_applies_depth_and_hidden_rules() {
+        let path = write_tar(
+            "data.tar",
+            &[
+                ("top.txt", b"1"),
+                (".hidden/x.txt", b"22"),
+                ("target/y.txt", b"333"),
+                ("sub/a.txt", b"4444"),
+                ("sub/deep/z.txt", b"55555"),
+            ],
+        );
+        let options = ScanOptions { ignore_dirs: vec!["target".into()], max_depth: 3, ..Default::default() };
+
+        // 压缩包本身的深度为 2，sub 为 3，sub/deep 超出深度限制
+        let node = expand(&path, 1024, &options, 2).unwrap();
+        assert_eq!(names(&node), ["top.txt", "sub", "sub/a.txt"]);
+        assert!(matches!(node, TreeNode::Directory { total_files: 2, total_size: 5, .. }));
+        assert_eq!(node.disk_size(), 1024);
+
+        let options = ScanOptions { all: true, ..Default::default() };
+        let node = expand(&path, 1024, &options, 2).unwrap();
+        assert_eq!(node.count_dirs(), 4);
+        assert!(matches!(node, TreeNode::Directory { total_files: 5, .. }));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn archives_beyond_depth_stay_files() {
+        let path = write_tar("deep.tar", &[("a.txt", b"1")]);
+        let options = ScanOptions { max_depth: 2, ..Default::default() };
+
+        let (plain, expanded) = expand_archives(vec![(path.clone(), 10)], &options, 3);
+        assert_eq!(plain, [(path.clone(), 10)]);
+        assert!(expanded.is_empty());
+
+        let (plain, expanded) = expand_archives(vec![(path.clone(), 10)], &options, 2);
+        assert!(plain.is_empty());
+        assert_eq!(expanded.len(), 1);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
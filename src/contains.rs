@@ -0,0 +1,51 @@
+use regex::bytes::Regex;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::filter::Filter;
+
+/// 检查是否为二进制文件时读取的字节数
+const BINARY_CHECK_SIZE: usize = 8192;
+
+/// 默认每个文件最多读取的字节数
+pub const DEFAULT_MAX_READ: u64 = 16 * 1024 * 1024;
+
+/// 按文件内容过滤：只保留内容匹配正则表达式的文本文件
+#[derive(Clone)]
+pub struct ContentFilter {
+    pub regex: Regex,
+    /// 每个文件最多读取的字节数，超出部分不参与匹配
+    pub max_read: u64,
+}
+
+impl ContentFilter {
+    pub fn new(pattern: &str, max_read: u64) -> Result<Self, regex::Error> {
+        Ok(ContentFilter {
+            regex: Regex::new(pattern)?,
+            max_read,
+        })
+    }
+}
+
+impl Filter for ContentFilter {
+    /// 文件内容是否匹配，无法读取的文件和二进制文件视为不匹配
+    fn matches(&self, path: &Path, _size: u64) -> bool {
+        // 打开管道会一直阻塞，只读取普通文件
+        if !path.is_file() {
+            return false;
+        }
+        let Ok(file) = File::open(path) else {
+            return false;
+        };
+        let mut content = Vec::new();
+        if file.take(self.max_read).read_to_end(&mut content).is_err() {
+            return false;
+        }
+        if content[..content.len().min(BINARY_CHECK_SIZE)].contains(&0) {
+            return false;
+        }
+        self.regex.is_match(&content)
+    }
+}
@@ -0,0 +1,266 @@
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::file_size::{format_count, format_size, format_size_delta};
+use crate::print::generate_tree_prefix;
+use crate::tree::TreeNode;
+
+/// 两次扫描之间发生变化的文件或目录
+pub struct DiffEntry {
+    name: String,
+    is_dir: bool,
+    /// 旧扫描中的 (文件数, 大小)，不存在时为 None
+    old: Option<(usize, u64)>,
+    /// 新扫描中的 (文件数, 大小)，不存在时为 None
+    new: Option<(usize, u64)>,
+    /// 发生变化的子项，目录在前
+    children: Vec<DiffEntry>,
+}
+
+/// 差异汇总
+#[derive(Default)]
+pub struct DiffStats {
+    pub added_files: usize,
+    pub added_bytes: u64,
+    pub removed_files: usize,
+    pub removed_bytes: u64,
+    pub resized_files: usize,
+    pub old_bytes: u64,
+    pub new_bytes: u64,
+}
+
+/// 节点的 (文件数, 大小)
+fn node_stats(node: &TreeNode) -> (usize, u64) {
+    match node {
+        TreeNode::File { size, .. } => (1, *size),
+        TreeNode::Directory { total_files, total_size, .. } => (*total_files, *total_size),
+    }
+}
+
+/// 按名称合并两边的子节点；管道、套接字等特殊文件不计入统计，也不参与比较
+fn merge_by_name<'a>(
+    old: &'a [TreeNode],
+    new: &'a [TreeNode],
+) -> BTreeMap<&'a str, (Option<&'a TreeNode>, Option<&'a TreeNode>)> {
+    let mut merged: BTreeMap<&str, (Option<&TreeNode>, Option<&TreeNode>)> = BTreeMap::new();
+    for node in old.iter().filter(|node| !node.is_special()) {
+        merged.entry(node.name()).or_default().0 = Some(node);
+    }
+    for node in new.iter().filter(|node| !node.is_special()) {
+        merged.entry(node.name()).or_default().1 = Some(node);
+    }
+    merged
+}
+
+/// 比较两个节点，没有变化时返回 None
+pub fn diff_nodes(old: Option<&TreeNode>, new: Option<&TreeNode>) -> Option<DiffEntry> {
+    let node = new.or(old)?;
+    let is_dir = matches!(node, TreeNode::Directory { .. });
+    let old_stats = old.map(node_stats);
+    let new_stats = new.map(node_stats);
+
+    let mut children = Vec::new();
+    if is_dir {
+        let empty = Vec::new();
+        let (old_files, old_dirs) = match old {
+            Some(TreeNode::Directory { files, dirs, .. }) => (files, dirs),
+            _ => (&empty, &empty),
+        };
+        let (new_files, new_dirs) = match new {
+            Some(TreeNode::Directory { files, dirs, .. }) => (files, dirs),
+            _ => (&empty, &empty),
+        };
+
+        for (old_child, new_child) in merge_by_name(old_dirs, new_dirs).into_values() {
+            children.extend(diff_nodes(old_child, new_child));
+        }
+        for (old_child, new_child) in merge_by_name(old_files, new_files).into_values() {
+            children.extend(diff_nodes(old_child, new_child));
+        }
+    }
+
+    let changed = old_stats != new_stats || !children.is_empty();
+    changed.then(|| DiffEntry {
+        name: node.name().to_string(),
+        is_dir,
+        old: old_stats,
+        new: new_stats,
+        children,
+    })
+}
+
+/// 文件数变化，例如 `+3 files`
+fn format_count_delta(old: usize, new: usize) -> String {
+    if new >= old {
+        format!("+{} files", format_count(new - old))
+    } else {
+        format!("-{} files", format_count(old - new))
+    }
+}
+
+/// 打印一个差异项的描述（不含树前缀）
+fn describe(entry: &DiffEntry) -> String {
+    let name = if entry.is_dir {
+        entry.name.blue().bold().to_string()
+    } else {
+        entry.name.clone()
+    };
+
+    match (entry.old, entry.new) {
+        (None, Some((files, size))) => {
+            let detail = if entry.is_dir {
+                format!("(+{} files, +{})", format_count(files), format_size(size))
+            } else {
+                format!("(+{})", format_size(size))
+            };
+            format!("{} {} {}", "+".green().bold(), name, detail.green())
+        }
+        (Some((files, size)), None) => {
+            let detail = if entry.is_dir {
+                format!("(-{} files, -{})", format_count(files), format_size(size))
+            } else {
+                format!("(-{})", format_size(size))
+            };
+            format!("{} {} {}", "-".red().bold(), name, detail.red())
+        }
+        (Some((old_files, old_size)), Some((new_files, new_size))) => {
+            let delta = format_size_delta(old_size, new_size);
+            let delta = if new_size >= old_size { delta.green() } else { delta.red() };
+            if entry.is_dir {
+                format!(
+                    "{} ({}, {} → {}, {})",
+                    name,
+                    format_count_delta(old_files, new_files).cyan(),
+                    format_size(old_size),
+                    format_size(new_size),
+                    delta
+                )
+            } else {
+                format!(
+                    "{} {} ({} → {}, {})",
+                    "~".yellow().bold(),
+                    name,
+                    format_size(old_size),
+                    format_size(new_size),
+                    delta
+                )
+            }
+        }
+        (None, None) => name,
+    }
+}
+
+/// 累计文件级别的差异
+fn collect_stats(entry: &DiffEntry, stats: &mut DiffStats) {
+    if entry.is_dir {
+        for child in &entry.children {
+            collect_stats(child, stats);
+        }
+        return;
+    }
+    match (entry.old, entry.new) {
+        (None, Some((_, size))) => {
+            stats.added_files += 1;
+            stats.added_bytes += size;
+        }
+        (Some((_, size)), None) => {
+            stats.removed_files += 1;
+            stats.removed_bytes += size;
+        }
+        (Some(_), Some(_)) => stats.resized_files += 1,
+        (None, None) => {}
+    }
+}
+
+/// 将带 +/- 标注的差异树写入 `out`
+pub fn write_diff_tree(entry: &DiffEntry, prefix: &str, is_last_items: &[bool], out: &mut dyn Write) -> io::Result<()> {
+    if is_last_items.is_empty() {
+        writeln!(out, "Directory: {}", describe(entry))?;
+    } else {
+        writeln!(out, "{}{}{}", prefix, generate_tree_prefix(is_last_items), describe(entry))?;
+    }
+
+    for (idx, child) in entry.children.iter().enumerate() {
+        let mut new_is_last_items = is_last_items.to_vec();
+        new_is_last_items.push(idx == entry.children.len() - 1);
+        write_diff_tree(child, prefix, &new_is_last_items, out)?;
+    }
+    Ok(())
+}
+
+/// 比较旧、新两棵树，将差异树写入 `out` 并返回汇总
+pub fn write_diff(old: Option<&TreeNode>, new: Option<&TreeNode>, out: &mut dyn Write) -> io::Result<DiffStats> {
+    let mut stats = DiffStats {
+        old_bytes: old.map(|n| n.total_size()).unwrap_or(0),
+        new_bytes: new.map(|n| n.total_size()).unwrap_or(0),
+        ..Default::default()
+    };
+
+    match diff_nodes(old, new) {
+        Some(entry) => {
+            write_diff_tree(&entry, "", &[], out)?;
+            collect_stats(&entry, &mut stats);
+        }
+        None => writeln!(out, "No differences found")?,
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn tree(files: &[(&str, u64)]) -> TreeNode {
+        let mut root = TreeNode::new_directory("root".into());
+        let sub = Path::new("root/sub");
+        root.insert_into(Path::new("root"), TreeNode::new_directory(sub.to_path_buf()));
+        for (name, size) in files {
+            let path = sub.join(name);
+            root.insert_into(sub, TreeNode::new_file(path, *size));
+        }
+        root
+    }
+
+    #[test]
+    fn identical_trees_have_no_diff() {
+        let old = tree(&[("a", 10), ("b", 20)]);
+        let new = tree(&[("a", 10), ("b", 20)]);
+        assert!(diff_nodes(Some(&old), Some(&new)).is_none());
+    }
+
+    #[test]
+    fn reports_added_removed_and_resized_files() {
+        let old = tree(&[("a", 10), ("b", 20)]);
+        let new = tree(&[("a", 15), ("c", 5)]);
+        let entry = diff_nodes(Some(&old), Some(&new)).unwrap();
+
+        let mut stats = DiffStats::default();
+        collect_stats(&entry, &mut stats);
+        assert_eq!((stats.added_files, stats.added_bytes), (1, 5));
+        assert_eq!((stats.removed_files, stats.removed_bytes), (1, 20));
+        assert_eq!(stats.resized_files, 1);
+
+        // 只包含发生变化的子目录
+        assert_eq!(entry.children.len(), 1);
+        let names: Vec<&str> = entry.children[0].children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn missing_side_counts_whole_tree() {
+        let new = tree(&[("a", 10)]);
+        let entry = diff_nodes(None, Some(&new)).unwrap();
+        assert_eq!(entry.old, None);
+        assert_eq!(entry.new, Some((1, 10)));
+    }
+
+    #[test]
+    fn special_files_are_not_compared() {
+        let old = tree(&[("a", 10)]);
+        let mut new = tree(&[("a", 10)]);
+        new.insert_into(Path::new("root/sub"), TreeNode::new_special("root/sub/fifo".into()));
+        assert!(diff_nodes(Some(&old), Some(&new)).is_none());
+    }
+}
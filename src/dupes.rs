@@ -0,0 +1,233 @@
+use colored::Colorize;
+use rayon::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::file_size::{format_count, format_size};
+use crate::hash::blake3_file;
+use crate::tree::TreeNode;
+
+/// 第一轮只比较文件开头的字节数
+const PARTIAL_HASH_SIZE: u64 = 4096;
+
+/// 内容完全相同的一组文件
+pub struct DuplicateSet {
+    pub size: u64,
+    /// 按路径排序，第一个视为原件，其余为重复副本
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateSet {
+    /// 重复副本占用的空间
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// 收集树中的所有文件
+fn collect_files(node: &TreeNode, out: &mut Vec<(PathBuf, u64)>) {
+    match node {
+        TreeNode::File { path, size, .. } => out.push((path.clone(), *size)),
+        TreeNode::Directory { files, dirs, .. } => {
+            for child in files.iter().chain(dirs.iter()) {
+                collect_files(child, out);
+            }
+        }
+    }
+}
+
+/// 去掉符号链接和指向同一 inode 的硬链接：它们不占用额外的空间，不是副本
+fn distinct_files(mut files: Vec<(PathBuf, u64)>) -> Vec<(PathBuf, u64)> {
+    // 同一 inode 保留路径最小的一个
+    files.sort();
+    let mut seen = HashSet::new();
+    files.retain(|(path, _)| match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => false,
+        Ok(metadata) => file_id(&metadata).is_none_or(|id| seen.insert(id)),
+        Err(_) => false,
+    });
+    files
+}
+
+/// 文件所在的设备和 inode
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// 将候选组按哈希再细分，只保留仍有多个文件的组
+fn split_by_hash(groups: Vec<Vec<(PathBuf, u64)>>, limit: Option<u64>) -> Vec<Vec<(PathBuf, u64)>> {
+    groups
+        .into_par_iter()
+        .flat_map(|group| {
+            let mut by_hash: HashMap<blake3::Hash, Vec<(PathBuf, u64)>> = HashMap::new();
+            let hashed: Vec<_> = group
+                .into_par_iter()
+                .filter_map(|(path, size)| {
+                    // 无法读取的文件不参与比较
+                    blake3_file(&path, limit).ok().map(|hash| (hash, (path, size)))
+                })
+                .collect();
+            for (hash, file) in hashed {
+                by_hash.entry(hash).or_default().push(file);
+            }
+            by_hash
+                .into_values()
+                .filter(|files| files.len() > 1)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// 查找重复文件：先按大小分组，再比较开头部分的哈希，最后比较完整内容的哈希
+pub fn find_duplicates(tree: &TreeNode) -> Vec<DuplicateSet> {
+    let mut files = Vec::new();
+    collect_files(tree, &mut files);
+
+    // 空文件没有浪费空间，不参与比较
+    let mut by_size: HashMap<u64, Vec<(PathBuf, u64)>> = HashMap::new();
+    for (path, size) in files {
+        if size > 0 {
+            by_size.entry(size).or_default().push((path, size));
+        }
+    }
+    let candidates: Vec<_> = by_size
+        .into_values()
+        .filter(|files| files.len() > 1)
+        .map(distinct_files)
+        .filter(|files| files.len() > 1)
+        .collect();
+
+    let candidates = split_by_hash(candidates, Some(PARTIAL_HASH_SIZE));
+    // 小文件的部分哈希已覆盖全部内容，无需再次读取
+    let (small, large): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|files| files[0].1 <= PARTIAL_HASH_SIZE);
+    let mut groups = small;
+    groups.extend(split_by_hash(large, None));
+
+    let mut sets: Vec<DuplicateSet> = groups
+        .into_iter()
+        .map(|files| {
+            let size = files[0].1;
+            let mut paths: Vec<PathBuf> = files.into_iter().map(|(path, _)| path).collect();
+            paths.sort();
+            DuplicateSet { size, paths }
+        })
+        .collect();
+    sets.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.paths.cmp(&b.paths)));
+    sets
+}
+
+/// 将重复文件组和各目录浪费的空间写入 `out`，`summary` 为 true 时之后输出汇总
+pub fn write_duplicates(sets: &[DuplicateSet], top: usize, summary: bool, out: &mut dyn Write) -> io::Result<()> {
+    if sets.is_empty() {
+        return writeln!(out, "No duplicate files found");
+    }
+
+    let shown = if top > 0 { top.min(sets.len()) } else { sets.len() };
+    writeln!(out, "{}", "Duplicate sets:".yellow().bold())?;
+    for set in &sets[..shown] {
+        writeln!(
+            out,
+            "{} copies of {}, wasted {}",
+            set.paths.len().to_string().cyan(),
+            format_size(set.size).yellow(),
+            format_size(set.wasted()).red().bold()
+        )?;
+        for (idx, path) in set.paths.iter().enumerate() {
+            let branch = if idx == set.paths.len() - 1 { "└── " } else { "├── " };
+            writeln!(out, "{}{}", branch, path.display())?;
+        }
+    }
+    if shown < sets.len() {
+        writeln!(out, "… and {} more sets", format_count(sets.len() - shown))?;
+    }
+
+    // 重复副本（除第一个外）按所在目录汇总
+    let mut per_dir: HashMap<&Path, (usize, u64)> = HashMap::new();
+    for set in sets {
+        for path in &set.paths[1..] {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            let entry = per_dir.entry(dir).or_default();
+            entry.0 += 1;
+            entry.1 += set.size;
+        }
+    }
+    let mut per_dir: Vec<_> = per_dir.into_iter().collect();
+    per_dir.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(b.0)));
+    if top > 0 {
+        per_dir.truncate(top);
+    }
+
+    writeln!(out, "\n{}", "Wasted space by directory:".yellow().bold())?;
+    for (dir, (copies, wasted)) in per_dir {
+        writeln!(
+            out,
+            "{:>12}  {:>6} copies  {}",
+            format_size(wasted).red(),
+            format_count(copies),
+            dir.display().to_string().blue().bold()
+        )?;
+    }
+    if !summary {
+        return Ok(());
+    }
+
+    let total_wasted: u64 = sets.iter().map(|set| set.wasted()).sum();
+    let total_copies: usize = sets.iter().map(|set| set.paths.len() - 1).sum();
+    writeln!(out, "\n{}", "Duplicates summary:".yellow().bold())?;
+    writeln!(out, "Duplicate sets: {}", format_count(sets.len()).blue().bold())?;
+    writeln!(out, "Extra copies  : {}", format_count(total_copies).blue().bold())?;
+    writeln!(out, "Wasted space  : {}", format_size(total_wasted).red().bold())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[cfg(unix)]
+    #[test]
+    fn groups_copies_but_not_links() {
+        let dir = std::env::temp_dir().join(format!("ntree-dupes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let large = vec![7u8; PARTIAL_HASH_SIZE as usize + 10];
+        let mut changed_tail = large.clone();
+        *changed_tail.last_mut().unwrap() = 8;
+
+        fs::write(dir.join("a.txt"), "same").unwrap();
+        fs::write(dir.join("sub/b.txt"), "same").unwrap();
+        fs::write(dir.join("c.txt"), "diff").unwrap();
+        fs::write(dir.join("empty1"), "").unwrap();
+        fs::write(dir.join("empty2"), "").unwrap();
+        fs::write(dir.join("big1"), &large).unwrap();
+        fs::write(dir.join("big2"), &large).unwrap();
+        fs::write(dir.join("big3"), &changed_tail).unwrap();
+        fs::hard_link(dir.join("c.txt"), dir.join("c-hard.txt")).unwrap();
+        std::os::unix::fs::symlink("c.txt", dir.join("c-link.txt")).unwrap();
+
+        let tree = Scanner::new(&dir).scan().unwrap();
+        let sets = find_duplicates(&tree);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let groups: Vec<(u64, Vec<PathBuf>)> = sets.into_iter().map(|set| (set.size, set.paths)).collect();
+        assert_eq!(
+            groups,
+            [
+                (large.len() as u64, vec![dir.join("big1"), dir.join("big2")]),
+                (4, vec![dir.join("a.txt"), dir.join("sub/b.txt")]),
+            ]
+        );
+    }
+}
@@ -15,6 +15,28 @@ pub fn format_size(bytes: u64) -> String {
     }
 }
 
+// 格式化大小的变化量，例如 "+1.20 MB" 或 "-300 bytes"
+pub fn format_size_delta(old: u64, new: u64) -> String {
+    if new >= old {
+        format!("+{}", format_size(new - old))
+    } else {
+        format!("-{}", format_size(old - new))
+    }
+}
+
+// 格式化数量，添加千位分隔符，例如 4213 -> "4,213"
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(c);
+    }
+    result
+}
+
 // 将字符串解析为字节大小
 pub fn parse_size(size_str: &str) -> Result<u64, String> {
     let size_str = size_str.trim().to_lowercase();
@@ -22,7 +44,7 @@ pub fn parse_size(size_str: &str) -> Result<u64, String> {
     let mut unit_part = String::new();
 
     for c in size_str.chars() {
-        if c.is_digit(10) || c == '.' {
+        if c.is_ascii_digit() || c == '.' {
             numeric_part.push(c);
         } else if !c.is_whitespace() {
             unit_part.push(c);
@@ -45,3 +67,24 @@ pub fn parse_size(size_str: &str) -> Result<u64, String> {
 
     Ok((value * multiplier as f64) as u64)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes_with_units() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10b"), Ok(10));
+        assert_eq!(parse_size("1KB"), Ok(1024));
+        assert_eq!(parse_size("1.5 MiB"), Ok(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_size(" 2g "), Ok(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+        assert!(parse_size("1.2.3kb").is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use regex::Regex;
+
+use std::path::Path;
+
+/// 扫描时判断文件是否被统计的条件，可以用 `and`、`or`、`not` 组合
+///
+/// 闭包 `Fn(&Path, u64) -> bool` 也实现了该 trait，可以直接作为过滤条件。
+pub trait Filter: Send + Sync {
+    /// 文件是否满足条件，`size` 为文件大小（字节）
+    fn matches(&self, path: &Path, size: u64) -> bool;
+
+    /// 两个条件都满足，先判断自身
+    fn and<F: Filter>(self, other: F) -> And<Self, F>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// 满足任一条件，先判断自身
+    fn or<F: Filter>(self, other: F) -> Or<Self, F>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// 不满足条件
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F> Filter for F
+where
+    F: Fn(&Path, u64) -> bool + Send + Sync,
+{
+    fn matches(&self, path: &Path, size: u64) -> bool {
+        self(path, size)
+    }
+}
+
+impl Filter for Box<dyn Filter> {
+    fn matches(&self, path: &Path, size: u64) -> bool {
+        (**self).matches(path, size)
+    }
+}
+
+/// 接受所有文件
+pub struct All;
+
+impl Filter for All {
+    fn matches(&self, _path: &Path, _size: u64) -> bool {
+        true
+    }
+}
+
+/// 按扩展名过滤（不含点，区分大小写）
+pub struct Ext(pub String);
+
+impl Filter for Ext {
+    fn matches(&self, path: &Path, _size: u64) -> bool {
+        path.extension().and_then(|e| e.to_str()) == Some(self.0.as_str())
+    }
+}
+
+/// 按文件大小过滤，包含上下限
+pub struct Size {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl Filter for Size {
+    fn matches(&self, _path: &Path, size: u64) -> bool {
+        size >= self.min && size <= self.max
+    }
+}
+
+/// 按路径匹配正则表达式过滤
+pub struct Pattern(pub Regex);
+
+impl Filter for Pattern {
+    fn matches(&self, path: &Path, _size: u64) -> bool {
+        self.0.is_match(path.to_str().unwrap_or(""))
+    }
+}
+
+/// 两个条件都满足
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: Filter, B: Filter> Filter for And<A, B> {
+    fn matches(&self, path: &Path, size: u64) -> bool {
+        self.0.matches(path, size) && self.1.matches(path, size)
+    }
+}
+
+/// 满足任一条件
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: Filter, B: Filter> Filter for Or<A, B> {
+    fn matches(&self, path: &Path, size: u64) -> bool {
+        self.0.matches(path, size) || self.1.matches(path, size)
+    }
+}
+
+/// 不满足条件
+pub struct Not<A>(pub A);
+
+impl<A: Filter> Filter for Not<A> {
+    fn matches(&self, path: &Path, size: u64) -> bool {
+        !self.0.matches(path, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_filters() {
+        let sources = Ext("rs".into()).or(Ext("toml".into()));
+        let not_tiny = Size { min: 0, max: 10 }.not();
+        let filter = sources.and(not_tiny);
+
+        assert!(filter.matches(Path::new("src/main.rs"), 100));
+        assert!(filter.matches(Path::new("Cargo.toml"), 11));
+        assert!(!filter.matches(Path::new("src/main.rs"), 10));
+        assert!(!filter.matches(Path::new("README.md"), 100));
+    }
+
+    #[test]
+    fn closures_and_boxed_filters() {
+        let large = |_: &Path, size: u64| size > 1024;
+        let boxed: Box<dyn Filter> = Box::new(Pattern(Regex::new(r"^target/").unwrap()).not());
+        let filter = boxed.and(large).or(All.not());
+
+        assert!(filter.matches(Path::new("src/big.bin"), 2048));
+        assert!(!filter.matches(Path::new("target/big.bin"), 2048));
+        assert!(!filter.matches(Path::new("src/small.bin"), 10));
+    }
+}
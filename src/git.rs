@@ -0,0 +1,255 @@
+use colored::{ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::tree::TreeNode;
+
+/// 文件在 git 中的状态，可用于 --git 过滤
+#[derive(Clone, Copy, PartialEq)]
+pub enum GitState {
+    Staged,
+    Modified,
+    Untracked,
+    Ignored,
+}
+
+impl GitState {
+    pub const ALL: [GitState; 4] = [GitState::Staged, GitState::Modified, GitState::Untracked, GitState::Ignored];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GitState::Staged => "staged",
+            GitState::Modified => "modified",
+            GitState::Untracked => "untracked",
+            GitState::Ignored => "ignored",
+        }
+    }
+
+    /// 与 `git status` 一致的配色
+    pub fn colorize(&self, text: &str) -> ColoredString {
+        match self {
+            GitState::Untracked | GitState::Modified => text.red(),
+            GitState::Staged => text.green(),
+            GitState::Ignored => text.dimmed(),
+        }
+    }
+
+    /// 判断 `git status --porcelain` 的两字符状态码是否属于该状态；
+    /// 已暂存后又修改的文件同时属于 staged 和 modified
+    pub fn matches(&self, code: &str) -> bool {
+        let mut chars = code.chars();
+        let index = chars.next().unwrap_or(' ');
+        let worktree = chars.next().unwrap_or(' ');
+        match self {
+            GitState::Untracked => index == '?',
+            GitState::Ignored => index == '!',
+            GitState::Staged => !matches!(index, ' ' | '?' | '!'),
+            GitState::Modified => !matches!(worktree, ' ' | '?' | '!'),
+        }
+    }
+}
+
+/// 状态码对应的所有状态
+pub fn states(code: &str) -> impl Iterator<Item = GitState> + '_ {
+    GitState::ALL.into_iter().filter(move |state| state.matches(code))
+}
+
+/// 运行 git 命令，失败时返回 git 的错误输出
+fn run_git(dir: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    Ok(output.stdout)
+}
+
+/// 仓库中有变化的文件：绝对路径到状态码；以 / 结尾的条目（整个目录被忽略或未跟踪）单独记录
+struct RepoStatus {
+    files: HashMap<PathBuf, String>,
+    dirs: HashMap<PathBuf, String>,
+}
+
+impl RepoStatus {
+    /// 读取包含 `dir` 的仓库的状态
+    fn read(dir: &Path) -> io::Result<RepoStatus> {
+        let toplevel = toplevel(dir)?;
+        let output = run_git(
+            dir,
+            &["status", "--porcelain=v1", "-z", "--ignored=matching", "--untracked-files=all"],
+        )?;
+
+        let mut status = RepoStatus { files: HashMap::new(), dirs: HashMap::new() };
+        let mut records = output.split(|&b| b == 0).filter(|r| !r.is_empty());
+        while let Some(record) = records.next() {
+            if record.len() < 4 {
+                continue;
+            }
+            let code = String::from_utf8_lossy(&record[..2]).into_owned();
+            let path = String::from_utf8_lossy(&record[3..]).into_owned();
+            // 重命名和复制之后跟着原路径
+            if code.contains(['R', 'C']) {
+                records.next();
+            }
+            match path.strip_suffix('/') {
+                Some(dir_path) => status.dirs.insert(toplevel.join(dir_path), code),
+                None => status.files.insert(toplevel.join(path), code),
+            };
+        }
+        Ok(status)
+    }
+
+    /// 文件的状态码，位于被忽略或未跟踪的目录中时继承目录的状态
+    fn lookup(&self, path: &Path) -> Option<&String> {
+        self.files
+            .get(path)
+            .or_else(|| path.ancestors().skip(1).find_map(|dir| self.dirs.get(dir)))
+    }
+}
+
+/// 读取仓库根目录的路径
+fn toplevel(dir: &Path) -> io::Result<PathBuf> {
+    let output = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&output).trim()))
+}
+
+/// 为树中的文件标注 git 状态；指定过滤条件时只保留该状态的文件
+pub fn annotate_tree(tree: &mut TreeNode, filter: Option<GitState>) -> io::Result<()> {
+    let root = tree.path().to_path_buf();
+    let status = RepoStatus::read(&root)?;
+    // git 输出的是绝对路径，树中的路径可能是相对路径
+    let canonical_root = fs::canonicalize(&root)?;
+
+    for node in tree.files_mut() {
+        if let TreeNode::File { path, git, .. } = node {
+            let relative = path.strip_prefix(&root).unwrap_or(path);
+            *git = status.lookup(&canonical_root.join(relative)).cloned();
+        }
+    }
+
+    if let Some(filter) = filter {
+        tree.retain_files(&|node| match node {
+            TreeNode::File { git: Some(code), .. } => filter.matches(code),
+            _ => false,
+        });
+    }
+    Ok(())
+}
+
+/// 统计子树中各状态的文件数，没有标注时为空
+pub fn count_states(node: &TreeNode) -> Vec<(GitState, usize)> {
+    fn visit(node: &TreeNode, counts: &mut [usize; 4]) {
+        match node {
+            TreeNode::File { git: Some(code), .. } => {
+                for (idx, state) in GitState::ALL.iter().enumerate() {
+                    if state.matches(code) {
+                        counts[idx] += 1;
+                    }
+                }
+            }
+            TreeNode::File { .. } => {}
+            TreeNode::Directory { files, dirs, .. } => {
+                for child in files.iter().chain(dirs.iter()) {
+                    visit(child, counts);
+                }
+            }
+        }
+    }
+    let mut counts = [0; 4];
+    visit(node, &mut counts);
+    GitState::ALL
+        .into_iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// 目录的提交历史统计
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct DirHistory {
+    /// 修改过该目录（含子目录）中文件的提交数
+    pub commits: usize,
+    /// 不同作者的数量
+    pub authors: usize,
+    /// 最后一次修改的 Unix 时间戳（秒）
+    pub last_change: u64,
+}
+
+/// 相对仓库根目录的路径到（提交数，作者，最后修改时间）
+type HistoryMap = HashMap<PathBuf, (usize, HashSet<String>, u64)>;
+
+/// 从 `git log` 汇总每个目录的提交数、作者和最后修改时间，键为相对仓库根目录的路径
+fn read_history(dir: &Path) -> io::Result<HistoryMap> {
+    // 还没有任何提交的仓库没有 HEAD，git log 会报错，视为没有历史
+    if run_git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+        return Ok(HashMap::new());
+    }
+
+    // 每个提交以 NUL 开头的一行作者和时间，之后是修改的文件列表
+    let output = run_git(
+        dir,
+        &["-c", "core.quotePath=off", "log", "--format=%x00%an%x09%at", "--name-only"],
+    )?;
+    let output = String::from_utf8_lossy(&output);
+
+    let mut history: HistoryMap = HashMap::new();
+    for commit in output.split('\0').filter(|c| !c.is_empty()) {
+        let mut lines = commit.lines();
+        let Some((author, timestamp)) = lines.next().and_then(|header| header.split_once('\t')) else {
+            continue;
+        };
+        let timestamp: u64 = timestamp.trim().parse().unwrap_or(0);
+
+        // 同一个提交在每个目录只计一次
+        let mut touched = HashSet::new();
+        for file in lines.filter(|line| !line.is_empty()) {
+            for ancestor in Path::new(file).ancestors().skip(1) {
+                if !touched.insert(ancestor.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+        for dir in touched {
+            let entry = history.entry(dir).or_default();
+            entry.0 += 1;
+            entry.1.insert(author.to_string());
+            entry.2 = entry.2.max(timestamp);
+        }
+    }
+    Ok(history)
+}
+
+/// 为树中的每个目录附加提交历史统计，没有提交记录的目录为 None
+pub fn annotate_history(tree: &mut TreeNode) -> io::Result<()> {
+    fn visit(node: &mut TreeNode, root: &Path, canonical: &Path, toplevel: &Path, history: &HistoryMap) {
+        if let TreeNode::Directory { path, dirs, history: dir_history, .. } = node {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            *dir_history = canonical
+                .join(relative)
+                .strip_prefix(toplevel)
+                .ok()
+                .and_then(|key| history.get(key))
+                .map(|(commits, authors, last_change)| DirHistory {
+                    commits: *commits,
+                    authors: authors.len(),
+                    last_change: *last_change,
+                });
+            for dir in dirs.iter_mut() {
+                visit(dir, root, canonical, toplevel, history);
+            }
+        }
+    }
+
+    let root = tree.path().to_path_buf();
+    let toplevel = fs::canonicalize(toplevel(&root)?)?;
+    let history = read_history(&root)?;
+    // git 输出的路径相对于仓库根目录，树中的路径可能是相对路径
+    let canonical_root = fs::canonicalize(&root)?;
+    visit(tree, &root, &canonical_root, &toplevel, &history);
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::tree::TreeNode;
+
+/// 读取文件时的缓冲区大小
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// 计算文件内容的 BLAKE3 哈希，`limit` 为 Some 时只读取开头的若干字节
+pub fn blake3_file(path: &Path, limit: Option<u64>) -> io::Result<blake3::Hash> {
+    let file = File::open(path)?;
+    let mut reader: Box<dyn Read> = match limit {
+        Some(limit) => Box::new(file.take(limit)),
+        None => Box::new(file),
+    };
+
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// 内容哈希算法
+#[derive(Clone, Copy, PartialEq)]
+pub enum HashAlgorithm {
+    Blake3,
+    Sha256,
+}
+
+/// 计算完整文件内容的哈希，返回十六进制字符串
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+    match algorithm {
+        HashAlgorithm::Blake3 => Ok(blake3_file(path, None)?.to_hex().to_string()),
+        HashAlgorithm::Sha256 => {
+            let mut file = File::open(path)?;
+            let mut hasher = Sha256::new();
+            let mut buffer = vec![0; BUFFER_SIZE];
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+            Ok(hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect())
+        }
+    }
+}
+
+/// 使用 rayon 线程池并行计算树中每个文件的哈希，返回无法读取的文件及错误
+pub fn hash_tree(tree: &mut TreeNode, algorithm: HashAlgorithm) -> Vec<(PathBuf, io::Error)> {
+    let mut files = tree.files_mut();
+    files
+        .par_iter_mut()
+        .filter_map(|node| {
+            let TreeNode::File { path, hash, .. } = &mut **node else {
+                return None;
+            };
+            // 管道、套接字等特殊文件没有内容，不计算哈希
+            if !path.is_file() {
+                return None;
+            }
+            match hash_file(path, algorithm) {
+                Ok(digest) => {
+                    *hash = Some(digest);
+                    None
+                }
+                Err(err) => Some((path.clone(), err)),
+            }
+        })
+        .collect()
+}
+
+/// 收集树中文件的哈希，键为相对根目录的路径（使用 / 分隔）；特殊文件没有内容，不出现在清单中
+fn collect_hashes(tree: &TreeNode) -> BTreeMap<String, Option<String>> {
+    fn visit(node: &TreeNode, root: &Path, out: &mut BTreeMap<String, Option<String>>) {
+        match node {
+            TreeNode::File { special: true, .. } => {}
+            TreeNode::File { path, hash, .. } => {
+                let relative = path.strip_prefix(root).unwrap_or(path);
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                out.insert(key, hash.clone());
+            }
+            TreeNode::Directory { files, dirs, .. } => {
+                for child in files.iter().chain(dirs.iter()) {
+                    visit(child, root, out);
+                }
+            }
+        }
+    }
+    let mut out = BTreeMap::new();
+    visit(tree, tree.path(), &mut out);
+    out
+}
+
+/// 清单文件位于扫描的根目录中时，它在清单里对应的相对路径
+fn manifest_key(tree: &TreeNode, manifest: &Path) -> Option<String> {
+    let root = fs::canonicalize(tree.path()).ok()?;
+    let manifest = fs::canonicalize(manifest).ok()?;
+    let relative = manifest.strip_prefix(root).ok()?;
+    Some(
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// 写出与 sha256sum/b3sum 兼容的清单，路径相对于扫描的根目录；清单文件本身不写入
+pub fn write_manifest<P: AsRef<Path>>(path: P, tree: &TreeNode) -> io::Result<usize> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    let own = manifest_key(tree, path);
+    let mut count = 0;
+    for (file, hash) in collect_hashes(tree) {
+        if own.as_ref() == Some(&file) {
+            continue;
+        }
+        if let Some(hash) = hash {
+            writeln!(writer, "{}  {}", hash, file)?;
+            count += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// 读取清单，返回相对路径到哈希的映射
+pub fn read_manifest<P: AsRef<Path>>(path: P) -> io::Result<BTreeMap<String, String>> {
+    parse_manifest(BufReader::new(File::open(path)?))
+}
+
+/// 解析清单内容，返回（相对路径，小写的哈希值）
+fn parse_manifest(reader: impl BufRead) -> io::Result<BTreeMap<String, String>> {
+    let mut entries = BTreeMap::new();
+    for line in reader.lines() {
+        let line = line?;
+        // 格式为 "<hash>  <path>"，二进制模式下路径前为 "*"
+        let Some((hash, file)) = line.split_once(' ') else {
+            continue;
+        };
+        let file = file.trim_start_matches([' ', '*']);
+        let file = file.strip_prefix("./").unwrap_or(file);
+        if !hash.is_empty() && !file.is_empty() {
+            entries.insert(file.to_string(), hash.to_lowercase());
+        }
+    }
+    Ok(entries)
+}
+
+/// 校验结果
+#[derive(Default)]
+pub struct VerifyReport {
+    pub ok: usize,
+    pub mismatched: Vec<String>,
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+/// 将重新扫描的树与清单比较，位于扫描目录中的清单文件 `manifest_path` 不算多出的文件
+pub fn verify(
+    tree: Option<&TreeNode>,
+    manifest: &BTreeMap<String, String>,
+    manifest_path: &Path,
+) -> VerifyReport {
+    let mut actual = tree.map(collect_hashes).unwrap_or_default();
+    if let Some(own) = tree.and_then(|tree| manifest_key(tree, manifest_path)) {
+        actual.remove(&own);
+    }
+    let mut report = VerifyReport::default();
+
+    for (file, expected) in manifest {
+        match actual.get(file) {
+            Some(Some(hash)) if hash == expected => report.ok += 1,
+            Some(_) => report.mismatched.push(file.clone()),
+            None => report.missing.push(file.clone()),
+        }
+    }
+    for file in actual.keys() {
+        if !manifest.contains_key(file) {
+            report.extra.push(file.clone());
+        }
+    }
+    report
+}
+
+/// 清单中的文件未出现在扫描结果中时，判断它是被过滤掉了还是确实不存在
+pub fn exists_relative(root: &Path, file: &str) -> bool {
+    fs::symlink_metadata(root.join(file)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sha256sum_manifests() {
+        let manifest = "ABCDEF  ./src/main.rs\n0123 *bin/tool\n\nnot-a-manifest-line\n";
+        let entries = parse_manifest(manifest.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["src/main.rs"], "abcdef");
+        assert_eq!(entries["bin/tool"], "0123");
+    }
+}
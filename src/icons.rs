@@ -0,0 +1,116 @@
+/// 默认的文件图标
+const FILE: char = '\u{f15b}';
+/// 默认的目录图标
+const DIR: char = '\u{e5ff}';
+
+/// 有专用图标的目录名
+const DIR_NAMES: &[(&str, char)] = &[
+    (".git", '\u{e5fb}'),
+    (".github", '\u{e5fd}'),
+    (".vscode", '\u{e70c}'),
+    ("node_modules", '\u{e5fa}'),
+];
+
+/// 有专用图标的文件名，优先于扩展名
+const FILE_NAMES: &[(&str, char)] = &[
+    ("Cargo.toml", '\u{e7a8}'),
+    ("Cargo.lock", '\u{e7a8}'),
+    ("Dockerfile", '\u{f308}'),
+    ("docker-compose.yml", '\u{f308}'),
+    ("docker-compose.yaml", '\u{f308}'),
+    (".dockerignore", '\u{f308}'),
+    (".gitignore", '\u{f1d3}'),
+    (".gitattributes", '\u{f1d3}'),
+    (".gitmodules", '\u{f1d3}'),
+    ("Makefile", '\u{e779}'),
+    ("LICENSE", '\u{f02d}'),
+    ("README.md", '\u{f48a}'),
+];
+
+/// 扩展名（小写，不含点）对应的图标
+const EXTENSIONS: &[(&str, char)] = &[
+    // 源代码
+    ("rs", '\u{e7a8}'),
+    ("py", '\u{e606}'),
+    ("js", '\u{e74e}'),
+    ("ts", '\u{e628}'),
+    ("go", '\u{e627}'),
+    ("c", '\u{e61e}'),
+    ("h", '\u{f0fd}'),
+    ("cpp", '\u{e61d}'),
+    ("hpp", '\u{f0fd}'),
+    ("java", '\u{e738}'),
+    ("rb", '\u{e739}'),
+    ("lua", '\u{e620}'),
+    ("r", '\u{f25d}'),
+    ("sh", '\u{f489}'),
+    ("bash", '\u{f489}'),
+    ("zsh", '\u{f489}'),
+    ("ps1", '\u{f489}'),
+    ("html", '\u{f13b}'),
+    ("css", '\u{e749}'),
+    // 配置和文档
+    ("toml", '\u{e6b2}'),
+    ("json", '\u{e60b}'),
+    ("yml", '\u{e6a8}'),
+    ("yaml", '\u{e6a8}'),
+    ("xml", '\u{f05c0}'),
+    ("lock", '\u{f023}'),
+    ("md", '\u{f48a}'),
+    ("txt", '\u{f15c}'),
+    ("csv", '\u{f1c3}'),
+    ("pdf", '\u{f1c1}'),
+    // 压缩包
+    ("zip", '\u{f410}'),
+    ("tar", '\u{f410}'),
+    ("gz", '\u{f410}'),
+    ("tgz", '\u{f410}'),
+    ("zst", '\u{f410}'),
+    ("tzst", '\u{f410}'),
+    ("xz", '\u{f410}'),
+    ("bz2", '\u{f410}'),
+    ("7z", '\u{f410}'),
+    ("rar", '\u{f410}'),
+    // 图片、音频和视频
+    ("png", '\u{f1c5}'),
+    ("jpg", '\u{f1c5}'),
+    ("jpeg", '\u{f1c5}'),
+    ("gif", '\u{f1c5}'),
+    ("bmp", '\u{f1c5}'),
+    ("webp", '\u{f1c5}'),
+    ("svg", '\u{f1c5}'),
+    ("ico", '\u{f1c5}'),
+    ("mp3", '\u{f001}'),
+    ("flac", '\u{f001}'),
+    ("wav", '\u{f001}'),
+    ("ogg", '\u{f001}'),
+    ("mp4", '\u{f03d}'),
+    ("mkv", '\u{f03d}'),
+    ("webm", '\u{f03d}'),
+    ("mov", '\u{f03d}'),
+    // 可执行文件和库
+    ("exe", '\u{f17a}'),
+    ("dll", '\u{f471}'),
+    ("so", '\u{f471}'),
+    ("wasm", '\u{e6a1}'),
+];
+
+fn lookup(table: &[(&str, char)], key: &str) -> Option<char> {
+    table.iter().find(|(name, _)| *name == key).map(|(_, icon)| *icon)
+}
+
+/// 目录的 Nerd Font 图标
+pub fn dir_icon(name: &str) -> char {
+    lookup(DIR_NAMES, name).unwrap_or(DIR)
+}
+
+/// 文件的 Nerd Font 图标，先按文件名，再按扩展名
+pub fn file_icon(name: &str) -> char {
+    if let Some(icon) = lookup(FILE_NAMES, name) {
+        return icon;
+    }
+    name.rsplit_once('.')
+        .filter(|(stem, _)| !stem.is_empty())
+        .and_then(|(_, ext)| lookup(EXTENSIONS, &ext.to_lowercase()))
+        .unwrap_or(FILE)
+}
@@ -0,0 +1,664 @@
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute, queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    terminal::{self, ClearType},
+};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::file_size::{format_count, format_size};
+use crate::tree::TreeNode;
+
+/// 排序方式
+#[derive(Clone, Copy, PartialEq)]
+enum SortBy {
+    Size,
+    Count,
+    Name,
+}
+
+impl SortBy {
+    fn label(self) -> &'static str {
+        match self {
+            SortBy::Size => "size",
+            SortBy::Count => "count",
+            SortBy::Name => "name",
+        }
+    }
+}
+
+/// 当前目录中的一行：子目录或文件在 TreeNode 中的下标
+#[derive(Clone, Copy)]
+enum Entry {
+    Dir(usize),
+    File(usize),
+}
+
+/// 底部等待用户输入的提示
+enum Prompt {
+    None,
+    /// 确认删除这些路径，附带它们在磁盘上的实际内容
+    ConfirmDelete(Vec<PathBuf>, DiskUsage),
+    /// 输入移动的目标目录
+    MoveTo(Vec<PathBuf>, String),
+}
+
+/// 路径在磁盘上的实际文件数和大小，包括被过滤、隐藏或忽略的文件
+#[derive(Default)]
+struct DiskUsage {
+    files: usize,
+    bytes: u64,
+}
+
+impl DiskUsage {
+    /// 递归统计路径下的所有文件，不跟随符号链接（与删除的范围一致）
+    fn add(&mut self, path: &Path) {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return;
+        };
+        if metadata.is_dir() {
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.flatten() {
+                    self.add(&entry.path());
+                }
+            }
+        } else {
+            self.files += 1;
+            self.bytes += metadata.len();
+        }
+    }
+}
+
+/// 一行显示所需的信息
+struct Row<'a> {
+    name: &'a str,
+    is_dir: bool,
+    files: usize,
+    size: u64,
+}
+
+/// 交互式浏览器的状态
+struct Browser {
+    root: TreeNode,
+    /// 从根目录到当前目录经过的子目录下标
+    stack: Vec<usize>,
+    /// 每一级目录中被选中的行，返回上一级时恢复
+    selected_stack: Vec<usize>,
+    selected: usize,
+    offset: usize,
+    sort_by: SortBy,
+    include_children: bool,
+    /// 被标记的文件和目录
+    marked: BTreeSet<PathBuf>,
+    prompt: Prompt,
+    /// 上一次操作的结果
+    status: String,
+    /// 删除时移动到该目录，而不是直接删除
+    trash_dir: Option<PathBuf>,
+    /// 浏览快照时树中的路径不一定对应当前的文件系统，禁止删除和移动
+    read_only: bool,
+}
+
+impl Browser {
+    fn new(root: TreeNode, include_children: bool, trash_dir: Option<PathBuf>, read_only: bool) -> Self {
+        Browser {
+            root,
+            stack: Vec::new(),
+            selected_stack: Vec::new(),
+            selected: 0,
+            offset: 0,
+            sort_by: SortBy::Size,
+            include_children,
+            marked: BTreeSet::new(),
+            prompt: Prompt::None,
+            status: String::new(),
+            trash_dir,
+            read_only,
+        }
+    }
+
+    /// 当前所在的目录节点
+    fn current(&self) -> &TreeNode {
+        let mut node = &self.root;
+        for &idx in &self.stack {
+            if let TreeNode::Directory { dirs, .. } = node {
+                node = &dirs[idx];
+            }
+        }
+        node
+    }
+
+    /// 目录节点的文件数和大小，根据 include_children 选择递归或仅当前目录
+    fn dir_stats(&self, node: &TreeNode) -> (usize, u64) {
+        match node {
+            TreeNode::Directory { total_files, total_size, direct_files, direct_size, .. } => {
+                if self.include_children {
+                    (*total_files, *total_size)
+                } else {
+                    (*direct_files, *direct_size)
+                }
+            }
+            TreeNode::File { size, .. } => (1, *size),
+        }
+    }
+
+    fn row<'a>(&self, node: &'a TreeNode) -> Row<'a> {
+        let (files, size) = self.dir_stats(node);
+        match node {
+            TreeNode::Directory { name, .. } => Row { name, is_dir: true, files, size },
+            TreeNode::File { name, .. } => Row { name, is_dir: false, files, size },
+        }
+    }
+
+    fn node<'a>(&self, dir: &'a TreeNode, entry: Entry) -> &'a TreeNode {
+        match (dir, entry) {
+            (TreeNode::Directory { dirs, .. }, Entry::Dir(i)) => &dirs[i],
+            (TreeNode::Directory { files, .. }, Entry::File(i)) => &files[i],
+            _ => dir,
+        }
+    }
+
+    /// 当前目录中按排序方式排列的所有行，目录在前
+    fn entries(&self) -> Vec<Entry> {
+        let dir = self.current();
+        let (files, dirs) = match dir {
+            TreeNode::Directory { files, dirs, .. } => (files.len(), dirs.len()),
+            TreeNode::File { .. } => (0, 0),
+        };
+        let mut entries: Vec<Entry> = (0..dirs)
+            .map(Entry::Dir)
+            .chain((0..files).map(Entry::File))
+            .collect();
+
+        entries.sort_by(|a, b| {
+            let ra = self.row(self.node(dir, *a));
+            let rb = self.row(self.node(dir, *b));
+            let by_kind = rb.is_dir.cmp(&ra.is_dir);
+            let by_field = match self.sort_by {
+                SortBy::Size => rb.size.cmp(&ra.size),
+                SortBy::Count => rb.files.cmp(&ra.files),
+                SortBy::Name => ra.name.cmp(rb.name),
+            };
+            by_kind.then(by_field).then_with(|| ra.name.cmp(rb.name))
+        });
+        entries
+    }
+
+    fn enter(&mut self) {
+        let entries = self.entries();
+        if let Some(Entry::Dir(idx)) = entries.get(self.selected) {
+            self.stack.push(*idx);
+            self.selected_stack.push(self.selected);
+            self.selected = 0;
+            self.offset = 0;
+        }
+    }
+
+    fn leave(&mut self) {
+        if self.stack.pop().is_some() {
+            self.selected = self.selected_stack.pop().unwrap_or(0);
+            self.offset = 0;
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let len = self.entries().len();
+        if len == 0 {
+            self.selected = 0;
+            return;
+        }
+        let selected = self.selected as isize + delta;
+        self.selected = selected.clamp(0, len as isize - 1) as usize;
+    }
+
+    /// 当前选中行的路径
+    fn selected_path(&self) -> Option<PathBuf> {
+        let dir = self.current();
+        self.entries()
+            .get(self.selected)
+            .map(|entry| self.node(dir, *entry).path().to_path_buf())
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(path) = self.selected_path() {
+            if !self.marked.remove(&path) {
+                self.marked.insert(path);
+            }
+            self.move_selection(1);
+        }
+    }
+
+    /// 操作的目标：有标记时为所有标记项，否则为当前选中行
+    fn targets(&self) -> Vec<PathBuf> {
+        if self.marked.is_empty() {
+            self.selected_path().into_iter().collect()
+        } else {
+            self.marked.iter().cloned().collect()
+        }
+    }
+
+    /// 目标路径在树中的总大小
+    fn targets_size(&self, targets: &[PathBuf]) -> u64 {
+        fn find<'a>(node: &'a TreeNode, target: &Path) -> Option<&'a TreeNode> {
+            if node.path() == target {
+                return Some(node);
+            }
+            match node {
+                TreeNode::Directory { files, dirs, .. } => files
+                    .iter()
+                    .chain(dirs.iter())
+                    .filter(|n| target.starts_with(n.path()))
+                    .find_map(|n| find(n, target)),
+                TreeNode::File { .. } => None,
+            }
+        }
+        targets
+            .iter()
+            .filter_map(|t| find(&self.root, t))
+            .map(|n| n.total_size())
+            .sum()
+    }
+
+    /// 文件系统修改后，树中的目录可能已被移除，按原路径重新定位
+    fn restore_position(&mut self, current: &Path) {
+        let selected_stack = std::mem::take(&mut self.selected_stack);
+        self.stack.clear();
+        let mut node = &self.root;
+        while let TreeNode::Directory { dirs, .. } = node {
+            match dirs.iter().position(|d| current.starts_with(d.path())) {
+                Some(idx) => {
+                    self.stack.push(idx);
+                    node = &dirs[idx];
+                }
+                None => break,
+            }
+        }
+        self.selected_stack = selected_stack;
+        self.selected_stack.truncate(self.stack.len());
+        self.selected_stack.resize(self.stack.len(), 0);
+    }
+
+    /// 删除目标（或移动到回收目录），并更新内存中的统计
+    fn delete(&mut self, targets: Vec<PathBuf>) {
+        let current = self.current().path().to_path_buf();
+        let mut done = 0;
+        let mut errors = Vec::new();
+
+        for target in &targets {
+            let result = match &self.trash_dir {
+                Some(trash_dir) => fs::create_dir_all(trash_dir)
+                    .and_then(|_| move_path(target, &unique_destination(trash_dir, target))),
+                None if target.is_dir() => fs::remove_dir_all(target),
+                None => fs::remove_file(target),
+            };
+            match result {
+                Ok(()) => {
+                    self.root.remove_path(target);
+                    self.marked.remove(target);
+                    done += 1;
+                }
+                Err(err) => errors.push(format!("{}: {}", target.display(), err)),
+            }
+        }
+
+        self.restore_position(&current);
+        let action = if self.trash_dir.is_some() { "Moved to trash" } else { "Deleted" };
+        self.status = report(action, done, &errors);
+    }
+
+    /// 将目标移动到另一个目录；目标目录在扫描范围内时同步更新树
+    fn move_to(&mut self, targets: Vec<PathBuf>, destination: &str) {
+        let destination = PathBuf::from(destination.trim());
+        if !destination.is_dir() {
+            self.status = format!("Not a directory: {}", destination.display());
+            return;
+        }
+
+        // 将目标目录换算成树中使用的路径形式
+        let tree_destination = match (fs::canonicalize(self.root.path()), fs::canonicalize(&destination)) {
+            (Ok(root), Ok(dest)) => dest
+                .strip_prefix(&root)
+                .ok()
+                .map(|rel| self.root.path().join(rel)),
+            _ => None,
+        };
+
+        let current = self.current().path().to_path_buf();
+        let mut done = 0;
+        let mut errors = Vec::new();
+
+        for target in &targets {
+            let Some(name) = target.file_name() else {
+                continue;
+            };
+            let new_path = destination.join(name);
+            if new_path.exists() {
+                errors.push(format!("{}: already exists", new_path.display()));
+                continue;
+            }
+            match move_path(target, &new_path) {
+                Ok(()) => {
+                    if let (Some(mut node), Some(tree_destination)) =
+                        (self.root.remove_path(target), &tree_destination)
+                    {
+                        node.rebase(tree_destination.join(name));
+                        // 目标目录未出现在树中（例如被过滤），则不再显示
+                        self.root.insert_into(tree_destination, node);
+                    }
+                    self.marked.remove(target);
+                    done += 1;
+                }
+                Err(err) => errors.push(format!("{}: {}", target.display(), err)),
+            }
+        }
+
+        self.restore_position(&current);
+        self.status = report("Moved", done, &errors);
+    }
+
+    /// 绘制整个界面
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+        // 标题两行，底部状态和帮助各一行
+        let visible_rows = (height as usize).saturating_sub(4).max(1);
+
+        let entries = self.entries();
+        if self.selected >= entries.len() {
+            self.selected = entries.len().saturating_sub(1);
+        }
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + visible_rows {
+            self.offset = self.selected + 1 - visible_rows;
+        }
+
+        let dir = self.current();
+        let (dir_files, dir_size) = self.dir_stats(dir);
+        let dir_path = match dir {
+            TreeNode::Directory { path, .. } => path.display().to_string(),
+            TreeNode::File { path, .. } => path.display().to_string(),
+        };
+
+        queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        queue!(
+            out,
+            SetAttribute(Attribute::Bold),
+            SetForegroundColor(Color::Blue),
+            Print(truncate(&dir_path, width)),
+            ResetColor,
+            SetAttribute(Attribute::Reset),
+            cursor::MoveTo(0, 1),
+            Print(truncate(
+                &format!(
+                    "{} files, {} | sort: {} | stats: {}",
+                    format_count(dir_files),
+                    format_size(dir_size),
+                    self.sort_by.label(),
+                    if self.include_children { "recursive" } else { "direct" }
+                ),
+                width
+            )),
+        )?;
+
+        for (line, entry) in entries.iter().enumerate().skip(self.offset).take(visible_rows) {
+            let row = self.row(self.node(dir, *entry));
+            let share = if dir_size > 0 { row.size as f64 / dir_size as f64 } else { 0.0 };
+            let bar_len = (share * 10.0).round() as usize;
+            let is_marked = self.marked.contains(self.node(dir, *entry).path());
+            let text = format!(
+                "{}{:>10} {:>8} [{:<10}] {}{}",
+                if is_marked { "*" } else { " " },
+                format_size(row.size),
+                format_count(row.files),
+                "#".repeat(bar_len.min(10)),
+                row.name,
+                if row.is_dir { "/" } else { "" }
+            );
+
+            queue!(out, cursor::MoveTo(0, (line - self.offset + 2) as u16))?;
+            if line == self.selected {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            if is_marked {
+                queue!(out, SetForegroundColor(Color::Yellow))?;
+            } else if row.is_dir {
+                queue!(out, SetForegroundColor(Color::Blue))?;
+            }
+            queue!(out, Print(truncate(&text, width)), ResetColor, SetAttribute(Attribute::Reset))?;
+        }
+
+        let status = match &self.prompt {
+            Prompt::None if !self.marked.is_empty() => {
+                format!("{} marked | {}", self.marked.len(), self.status)
+            }
+            Prompt::None => self.status.clone(),
+            Prompt::ConfirmDelete(targets, usage) => {
+                // 树可能经过过滤，同时显示磁盘上的实际内容和树中显示的大小
+                let size = format!(
+                    "{} files, {} on disk; {} shown",
+                    format_count(usage.files),
+                    format_size(usage.bytes),
+                    format_size(self.targets_size(targets))
+                );
+                match &self.trash_dir {
+                    Some(trash_dir) => format!(
+                        "Move {} items ({}) to {}? [y/N]",
+                        targets.len(),
+                        size,
+                        trash_dir.display()
+                    ),
+                    None => format!(
+                        "Delete {} items ({}) including all directory contents? [y/N]",
+                        targets.len(),
+                        size
+                    ),
+                }
+            }
+            Prompt::MoveTo(targets, input) => format!("Move {} items to: {}", targets.len(), input),
+        };
+
+        queue!(
+            out,
+            cursor::MoveTo(0, height.saturating_sub(2)),
+            SetForegroundColor(Color::Yellow),
+            Print(truncate(&status, width)),
+            ResetColor,
+            cursor::MoveTo(0, height.saturating_sub(1)),
+            SetAttribute(Attribute::Dim),
+            Print(truncate(
+                if self.read_only {
+                    "↑↓ move  → enter  ← back  s/c/n sort  t direct/recursive  space mark  q quit  (snapshot, read-only)"
+                } else {
+                    "↑↓ move  → enter  ← back  s/c/n sort  t direct/recursive  space mark  d delete  m move  q quit"
+                },
+                width
+            )),
+            SetAttribute(Attribute::Reset),
+        )?;
+        out.flush()
+    }
+}
+
+/// 回收目录中不与已有文件重名的目标路径
+fn unique_destination(dir: &Path, source: &Path) -> PathBuf {
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut candidate = dir.join(&name);
+    let mut suffix = 1;
+    while candidate.exists() {
+        candidate = dir.join(format!("{}.{}", name, suffix));
+        suffix += 1;
+    }
+    candidate
+}
+
+/// 移动文件或目录；目标在另一个文件系统上时 rename 会失败（EXDEV），改为复制后删除
+fn move_path(source: &Path, destination: &Path) -> io::Result<()> {
+    match fs::rename(source, destination) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(err) = copy_recursive(source, destination) {
+                // 复制失败时清理已复制的部分，源文件保持不变
+                let _ = remove_path(destination);
+                return Err(err);
+            }
+            remove_path(source)
+        }
+        result => result,
+    }
+}
+
+/// 递归复制目录，符号链接按链接本身复制
+fn copy_recursive(source: &Path, destination: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(source)?.file_type();
+    if file_type.is_dir() {
+        fs::create_dir(destination)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if file_type.is_symlink() {
+        copy_symlink(source, destination)
+    } else {
+        fs::copy(source, destination).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, destination)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::copy(source, destination).map(|_| ())
+}
+
+/// 删除文件或整个目录，符号链接只删除链接本身
+fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// 汇总一次批量操作的结果
+fn report(action: &str, done: usize, errors: &[String]) -> String {
+    match errors.first() {
+        None => format!("{} {} items", action, done),
+        Some(first) => format!("{} {} items, {} failed ({})", action, done, errors.len(), first),
+    }
+}
+
+/// 按字符截断到终端宽度
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// 恢复终端状态，即使浏览过程中出错也会执行
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// 启动交互式浏览器，树结构只构建一次
+///
+/// `read_only` 为 true 时（浏览快照）禁止删除和移动。
+pub fn browse(tree: TreeNode, include_children: bool, trash_dir: Option<PathBuf>, read_only: bool) -> io::Result<()> {
+    let mut browser = Browser::new(tree, include_children, trash_dir, read_only);
+    let mut stdout = io::stdout();
+
+    terminal::enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    loop {
+        browser.draw(&mut stdout)?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        let page = terminal::size()?.1.saturating_sub(4).max(1) as isize;
+
+        // 有提示时按键交给提示处理
+        match std::mem::replace(&mut browser.prompt, Prompt::None) {
+            Prompt::None => {}
+            Prompt::ConfirmDelete(targets, _) => {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => browser.delete(targets),
+                    _ => browser.status = "Cancelled".to_string(),
+                }
+                continue;
+            }
+            Prompt::MoveTo(targets, mut input) => {
+                match key.code {
+                    KeyCode::Enter => browser.move_to(targets, &input),
+                    KeyCode::Esc => browser.status = "Cancelled".to_string(),
+                    KeyCode::Backspace => {
+                        input.pop();
+                        browser.prompt = Prompt::MoveTo(targets, input);
+                    }
+                    KeyCode::Char(c) => {
+                        input.push(c);
+                        browser.prompt = Prompt::MoveTo(targets, input);
+                    }
+                    _ => browser.prompt = Prompt::MoveTo(targets, input),
+                }
+                continue;
+            }
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Up | KeyCode::Char('k') => browser.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => browser.move_selection(1),
+            KeyCode::PageUp => browser.move_selection(-page),
+            KeyCode::PageDown => browser.move_selection(page),
+            KeyCode::Home => browser.move_selection(isize::MIN / 2),
+            KeyCode::End => browser.move_selection(isize::MAX / 2),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => browser.enter(),
+            KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => browser.leave(),
+            KeyCode::Char('s') => browser.sort_by = SortBy::Size,
+            KeyCode::Char('c') => browser.sort_by = SortBy::Count,
+            KeyCode::Char('n') => browser.sort_by = SortBy::Name,
+            KeyCode::Char('t') | KeyCode::Tab => browser.include_children = !browser.include_children,
+            KeyCode::Char(' ') => browser.toggle_mark(),
+            KeyCode::Char('u') => browser.marked.clear(),
+            KeyCode::Char('d') | KeyCode::Delete | KeyCode::Char('m') if browser.read_only => {
+                browser.status = "Snapshots are read-only".to_string();
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                let targets = browser.targets();
+                if !targets.is_empty() {
+                    let mut usage = DiskUsage::default();
+                    for target in &targets {
+                        usage.add(target);
+                    }
+                    browser.prompt = Prompt::ConfirmDelete(targets, usage);
+                }
+            }
+            KeyCode::Char('m') => {
+                let targets = browser.targets();
+                if !targets.is_empty() {
+                    browser.prompt = Prompt::MoveTo(targets, String::new());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
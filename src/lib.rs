@@ -0,0 +1,31 @@
+//! ntree 的核心功能：并行扫描目录树、过滤、统计和输出。
+//!
+//! 使用 [`Scanner`] 构建扫描，得到的 [`TreeNode`] 可以交给 `print`、`diff`、
+//! `dupes` 等模块继续处理，`ntree` 命令行工具本身也是这样组合的。
+
+pub mod archive;
+pub mod contains;
+pub mod diff;
+pub mod dupes;
+pub mod file_size;
+pub mod filter;
+pub mod git;
+pub mod hash;
+pub mod icons;
+pub mod interactive;
+pub mod lines;
+pub mod list_files;
+pub mod ls_colors;
+pub mod magic;
+pub mod print;
+pub mod scanner;
+pub mod snapshot;
+pub mod tree;
+pub mod trend;
+#[cfg(target_os = "linux")]
+pub mod watch;
+
+pub use list_files::FileStats;
+pub use filter::Filter;
+pub use scanner::{glob_to_regex, ScanOptions, Scanner};
+pub use tree::TreeNode;
@@ -0,0 +1,293 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::AddAssign;
+use std::path::Path;
+
+use crate::tree::TreeNode;
+
+/// 检查是否为二进制文件时读取的字节数
+const BINARY_CHECK_SIZE: usize = 8192;
+
+/// 行数统计
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct LineCounts {
+    pub total: usize,
+    pub code: usize,
+    pub comment: usize,
+    pub blank: usize,
+}
+
+impl AddAssign for LineCounts {
+    fn add_assign(&mut self, other: LineCounts) {
+        self.total += other.total;
+        self.code += other.code;
+        self.comment += other.comment;
+        self.blank += other.blank;
+    }
+}
+
+/// 一种语言的注释语法
+struct Language {
+    name: &'static str,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+}
+
+const C_STYLE: (&str, &str) = ("/*", "*/");
+
+/// 根据扩展名识别语言，未知的文本文件只统计空行
+fn detect_language(path: &Path) -> Language {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+    let (name, line_comments, block_comment): (&str, &[&str], _) = match ext.as_str() {
+        "rs" => ("Rust", &["//"], Some(C_STYLE)),
+        "c" | "h" => ("C", &["//"], Some(C_STYLE)),
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => ("C++", &["//"], Some(C_STYLE)),
+        "cs" => ("C#", &["//"], Some(C_STYLE)),
+        "go" => ("Go", &["//"], Some(C_STYLE)),
+        "java" => ("Java", &["//"], Some(C_STYLE)),
+        "kt" | "kts" => ("Kotlin", &["//"], Some(C_STYLE)),
+        "scala" => ("Scala", &["//"], Some(C_STYLE)),
+        "swift" => ("Swift", &["//"], Some(C_STYLE)),
+        "dart" => ("Dart", &["//"], Some(C_STYLE)),
+        "js" | "mjs" | "cjs" | "jsx" => ("JavaScript", &["//"], Some(C_STYLE)),
+        "ts" | "tsx" => ("TypeScript", &["//"], Some(C_STYLE)),
+        "css" => ("CSS", &[], Some(C_STYLE)),
+        "py" | "pyi" => ("Python", &["#"], None),
+        "rb" => ("Ruby", &["#"], None),
+        "pl" | "pm" => ("Perl", &["#"], None),
+        "r" => ("R", &["#"], None),
+        "jl" => ("Julia", &["#"], None),
+        "sh" | "bash" | "zsh" | "fish" => ("Shell", &["#"], None),
+        "ps1" => ("PowerShell", &["#"], Some(("<#", "#>"))),
+        "yaml" | "yml" => ("YAML", &["#"], None),
+        "toml" => ("TOML", &["#"], None),
+        "ini" | "cfg" | "conf" => ("Config", &["#", ";"], None),
+        "sql" => ("SQL", &["--"], Some(C_STYLE)),
+        "lua" => ("Lua", &["--"], Some(("--[[", "]]"))),
+        "hs" => ("Haskell", &["--"], Some(("{-", "-}"))),
+        "tex" => ("TeX", &["%"], None),
+        "m" => ("MATLAB", &["%"], Some(("%{", "%}"))),
+        "html" | "htm" => ("HTML", &[], Some(("<!--", "-->"))),
+        "xml" | "svg" => ("XML", &[], Some(("<!--", "-->"))),
+        "vue" => ("Vue", &["//"], Some(("<!--", "-->"))),
+        "md" | "markdown" => ("Markdown", &[], Some(("<!--", "-->"))),
+        "json" => ("JSON", &[], None),
+        "csv" => ("CSV", &[], None),
+        "txt" => ("Text", &[], None),
+        _ => match file_name {
+            "Dockerfile" => ("Dockerfile", &["#"], None),
+            "Makefile" => ("Makefile", &["#"], None),
+            "CMakeLists.txt" => ("CMake", &["#"], None),
+            _ => ("Other", &[], None),
+        },
+    };
+    Language { name, line_comments, block_comment }
+}
+
+/// 逐行统计时的状态，按语言区分注释行
+struct LineCounter<'a> {
+    language: &'a Language,
+    counts: LineCounts,
+    in_block: bool,
+}
+
+impl<'a> LineCounter<'a> {
+    fn new(language: &'a Language) -> Self {
+        LineCounter { language, counts: LineCounts::default(), in_block: false }
+    }
+
+    fn add_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+        self.counts.total += 1;
+
+        if self.in_block {
+            self.counts.comment += 1;
+            if let Some((_, end)) = self.language.block_comment {
+                if let Some(idx) = trimmed.find(end) {
+                    self.in_block = self.opens_block(&trimmed[idx + end.len()..]);
+                }
+            }
+            return;
+        }
+        if trimmed.is_empty() {
+            self.counts.blank += 1;
+            return;
+        }
+        // 块注释先于行注释检查：Lua 的 `--[[` 和 MATLAB 的 `%{` 以行注释前缀开头
+        if let Some((start, _)) = self.language.block_comment {
+            if trimmed.starts_with(start) {
+                self.counts.comment += 1;
+                self.in_block = self.opens_block(trimmed);
+                return;
+            }
+        }
+        if self.language.line_comments.iter().any(|prefix| trimmed.starts_with(prefix)) {
+            self.counts.comment += 1;
+            return;
+        }
+        // 代码之后开始的块注释（如 `x = 1; /* start`）同样延续到后面的行
+        self.counts.code += 1;
+        self.in_block = self.opens_block(trimmed);
+    }
+
+    /// 这段文本中是否有未结束的块注释；行注释之后的内容不算
+    fn opens_block(&self, mut text: &str) -> bool {
+        let Some((start, end)) = self.language.block_comment else {
+            return false;
+        };
+        while let Some(idx) = text.find(start) {
+            if self.language.line_comments.iter().any(|prefix| text[..idx].contains(prefix)) {
+                return false;
+            }
+            let rest = &text[idx + start.len()..];
+            match rest.find(end) {
+                Some(end_idx) => text = &rest[end_idx + end.len()..],
+                None => return true,
+            }
+        }
+        false
+    }
+}
+
+/// 逐行读取文本内容并统计行数，按语言区分注释行
+fn count_text(mut reader: impl BufRead, language: &Language) -> io::Result<LineCounts> {
+    let mut counter = LineCounter::new(language);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        counter.add_line(&String::from_utf8_lossy(&line));
+        line.clear();
+    }
+    Ok(counter.counts)
+}
+
+/// 统计单个文件的行数，二进制文件返回 None
+///
+/// 先读取开头的 `BINARY_CHECK_SIZE` 字节判断是否为二进制文件，文本文件再逐行读取，
+/// 不会把大文件整个读入内存。
+pub fn count_file(path: &Path) -> Option<LineCounts> {
+    // 管道、套接字等特殊文件没有可统计的内容
+    if !path.is_file() {
+        return None;
+    }
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut head = Vec::with_capacity(BINARY_CHECK_SIZE);
+    (&mut reader).take(BINARY_CHECK_SIZE as u64).read_to_end(&mut head).ok()?;
+    if head.contains(&0) {
+        return None;
+    }
+    count_text(head.chain(reader), &detect_language(path)).ok()
+}
+
+/// 文件所属的语言名称
+pub fn language_name(path: &Path) -> &'static str {
+    detect_language(path).name
+}
+
+/// 汇总各目录的行数
+fn aggregate(node: &mut TreeNode) -> Option<LineCounts> {
+    match node {
+        TreeNode::File { lines, .. } => *lines,
+        TreeNode::Directory { files, dirs, lines, .. } => {
+            let mut total: Option<LineCounts> = None;
+            for child in files.iter_mut().chain(dirs.iter_mut()) {
+                if let Some(child_lines) = aggregate(child) {
+                    *total.get_or_insert_with(LineCounts::default) += child_lines;
+                }
+            }
+            *lines = total;
+            total
+        }
+    }
+}
+
+/// 并行统计树中每个文件的行数，并汇总到各级目录
+pub fn count_tree(tree: &mut TreeNode) {
+    let mut files = tree.files_mut();
+    files.par_iter_mut().for_each(|node| {
+        if let TreeNode::File { path, lines, .. } = &mut **node {
+            *lines = count_file(path);
+        }
+    });
+    aggregate(tree);
+}
+
+/// 按语言汇总行数，按代码行数从多到少排序
+pub fn by_language(tree: &TreeNode) -> Vec<(&'static str, usize, LineCounts)> {
+    fn visit(node: &TreeNode, out: &mut HashMap<&'static str, (usize, LineCounts)>) {
+        match node {
+            TreeNode::File { path, lines: Some(lines), .. } => {
+                let entry = out.entry(language_name(path)).or_default();
+                entry.0 += 1;
+                entry.1 += *lines;
+            }
+            TreeNode::File { .. } => {}
+            TreeNode::Directory { files, dirs, .. } => {
+                for child in files.iter().chain(dirs.iter()) {
+                    visit(child, out);
+                }
+            }
+        }
+    }
+    let mut out = HashMap::new();
+    visit(tree, &mut out);
+    let mut languages: Vec<_> = out
+        .into_iter()
+        .map(|(name, (files, lines))| (name, files, lines))
+        .collect();
+    languages.sort_by(|a, b| b.2.code.cmp(&a.2.code).then_with(|| a.0.cmp(b.0)));
+    languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(text: &str, file_name: &str) -> LineCounts {
+        count_text(text.as_bytes(), &detect_language(Path::new(file_name))).unwrap()
+    }
+
+    #[test]
+    fn counts_code_comment_and_blank_lines() {
+        let counts = count("// header\nfn main() {}\n\n/* a\n   b */\nlet x = 1;\n", "main.rs");
+        assert_eq!(counts.total, 6);
+        assert_eq!(counts.code, 2);
+        assert_eq!(counts.comment, 3);
+        assert_eq!(counts.blank, 1);
+    }
+
+    #[test]
+    fn block_comments_starting_with_line_prefix() {
+        let lua = count("--[[\nblock\n]]\nprint(1)\n-- note\n", "init.lua");
+        assert_eq!((lua.code, lua.comment), (1, 4));
+
+        let matlab = count("%{\nblock\n%}\ny = 1;\n", "script.m");
+        assert_eq!((matlab.code, matlab.comment), (1, 3));
+    }
+
+    #[test]
+    fn block_comments_opened_after_code() {
+        let counts = count("x = 1; /* start\nstill comment\nend */\ny = 2; // not /* a block\nz = 3;\n", "main.c");
+        assert_eq!((counts.code, counts.comment), (3, 2));
+
+        let closed = count("a /* one */ b /* two\n*/ c\n", "main.c");
+        assert_eq!((closed.code, closed.comment), (1, 1));
+    }
+
+    #[test]
+    fn unknown_languages_count_text_as_code() {
+        let counts = count("# not a comment\n\ntext\r\n", "notes.unknown");
+        assert_eq!(counts.total, 3);
+        assert_eq!(counts.code, 2);
+        assert_eq!(counts.blank, 1);
+    }
+}
@@ -1,13 +1,15 @@
+use rayon::iter::Either;
 use rayon::prelude::*;
-use regex::Regex;
 
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
 
 // use crate::regex::Regex;
+use crate::archive;
+use crate::scanner::ScanOptions;
 use crate::tree::TreeNode;
-use crate::print::{print_tree_num, print_tree_file}; // 修改导入
+use crate::print::{formatter_for, DisplayOptions};
 
 // Structure to hold file counting statistics
 pub struct FileStats {
@@ -16,223 +18,219 @@ pub struct FileStats {
     pub total_bytes: u64,
 }
 
+impl FileStats {
+    /// 统计树中的文件数、目录数（不含根目录）和总大小
+    pub fn from_tree(tree: &TreeNode) -> Self {
+        match tree {
+            TreeNode::Directory { total_files, total_size, .. } => FileStats {
+                total_files: *total_files,
+                total_dirs: tree.count_dirs(),
+                total_bytes: *total_size,
+            },
+            TreeNode::File { size, .. } => FileStats {
+                total_files: 1,
+                total_dirs: 0,
+                total_bytes: *size,
+            },
+        }
+    }
+}
 
-/// 处理单个目录的文件和子目录
-fn process_directory_entries(
-    entries: Vec<fs::DirEntry>,
-    ext: &str,
-    ignore_dirs: &[String],
-    min_size: u64,
-    max_size: u64,  // 添加max_size参数
-    pattern: Option<&Regex>,  // 添加正则表达式参数
-) -> (Vec<(PathBuf, u64)>, Vec<PathBuf>) {
-    // 使用线程安全的数据结构来存储结果
-    let files = Arc::new(Mutex::new(Vec::with_capacity(entries.len())));
-    let dirs = Arc::new(Mutex::new(Vec::with_capacity(entries.len())));
 
+/// 将目录条目分为文件和子目录，跳过隐藏和忽略的条目；文件尚未经过过滤
+fn split_directory_entries(
+    entries: Vec<fs::DirEntry>,
+    options: &ScanOptions,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
     // 并行处理所有条目
-    entries.par_iter().for_each(|entry| {
-        let path = entry.path();
-
-        // Check if directory should be ignored
-        if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
-            if ignore_dirs.iter().any(|ignored| ignored == dir_name) {
-                return;
+    let entries: Vec<(PathBuf, bool)> = entries
+        .par_iter()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+            // 以点开头的文件和目录被视为隐藏（-a 时保留）；Check if directory should be ignored
+            if options.skips(name) {
+                return None;
             }
-        }
 
-        // 优化：仅获取一次元数据
-        if let Ok(metadata) = fs::metadata(&path) {
-            // 以点开头的文件和目录被视为隐藏
-            let is_hidden_file = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .map(|s| s.starts_with('.'))
-                .unwrap_or(false);
-
-            if is_hidden_file {
-                return;
-            }
-
-            // 已经有了元数据，直接使用
-            if metadata.is_file() {
-                // 检查文件大小是否满足最小和最大要求
-                let file_size = metadata.len();
-                if file_size < min_size || file_size > max_size {
-                    return; // 跳过不符合大小要求的文件
-                }
-
-                // 检查文件名是否匹配正则表达式
-                if let Some(pattern) = pattern {
-                    if !pattern.is_match(path.to_str().unwrap_or("")) {
-                        return;  // 跳过不匹配的文件
-                    }
-                }
-
-                if ext.is_empty() || path.extension().and_then(|e| e.to_str()) == Some(ext) {
-                    let mut files = files.lock().unwrap();
-                    files.push((path, file_size)); // 将文件大小一并保存
-                }
+            // 条目类型来自目录本身，只有符号链接需要读取目标的元数据
+            let file_type = entry.file_type().ok()?;
+            let is_dir = if file_type.is_symlink() {
+                fs::metadata(&path).ok()?.is_dir()
             } else {
-                // 不是文件，则认为是目录
-                let mut dirs = dirs.lock().unwrap();
-                dirs.push(path);
-            }
+                file_type.is_dir()
+            };
+            Some((path, is_dir))
+        })
+        .collect();
+
+    let mut files = Vec::with_capacity(entries.len());
+    let mut dirs = Vec::new();
+    for (path, is_dir) in entries {
+        if is_dir {
+            dirs.push(path);
+        } else {
+            files.push(path);
         }
-    });
-
-    // 获取结果并排序
-    let mut files = Arc::try_unwrap(files).unwrap().into_inner().unwrap();
-    let mut dirs = Arc::try_unwrap(dirs).unwrap().into_inner().unwrap();
+    }
 
     // 对文件和目录进行排序，保持顺序稳定
-    files.sort_by(|(a, _), (b, _)| a.file_name().cmp(&b.file_name()));
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
     dirs.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
-
     (files, dirs)
 }
 
+/// 并行读取文件的当前大小并按过滤条件筛选，已不存在的文件被跳过
+///
+/// 返回普通文件及其大小，以及与 ls 一样列出的管道、套接字等特殊文件。
+fn filter_files(paths: Vec<PathBuf>, options: &ScanOptions) -> (Vec<(PathBuf, u64)>, Vec<PathBuf>) {
+    paths
+        .into_par_iter()
+        .filter_map(|path| {
+            let metadata = fs::metadata(&path).ok()?;
+            if metadata.is_dir() {
+                return None;
+            }
+            let size = metadata.len();
+            if !metadata.is_file() {
+                return options.filter.matches(&path, size).then_some(Either::Right(path));
+            }
+            // 压缩包的过滤条件在展开后应用于包内的文件
+            if options.archives && archive::is_archive(&path) {
+                return Some(Either::Left((path, size)));
+            }
+            options.filter.matches(&path, size).then_some(Either::Left((path, size)))
+        })
+        .partition_map(|file| file)
+}
+
+/// 处理单个目录的文件和子目录，返回普通文件、特殊文件和子目录
+pub fn process_directory_entries(
+    entries: Vec<fs::DirEntry>,
+    options: &ScanOptions,
+) -> (Vec<(PathBuf, u64)>, Vec<PathBuf>, Vec<PathBuf>) {
+    let (files, dirs) = split_directory_entries(entries, options);
+    let (files, special) = filter_files(files, options);
+    (files, special, dirs)
+}
+
+/// 创建目录中的文件节点，普通文件和特殊文件按名称排序
+pub fn file_nodes(files: Vec<(PathBuf, u64)>, special: Vec<PathBuf>) -> Vec<TreeNode> {
+    let mut nodes: Vec<TreeNode> = files
+        .into_iter()
+        .map(|(path, size)| TreeNode::new_file(path, size))
+        .chain(special.into_iter().map(TreeNode::new_special))
+        .collect();
+    nodes.sort_by(|a, b| a.name().cmp(b.name()));
+    nodes
+}
+
 /// 构建文件系统的树结构
 pub fn build_directory_tree<P: AsRef<Path>>(
     dir_path: P, 
-    ext: &str,
-    ignore_dirs: &[String],
-    min_size: u64,
-    max_size: u64,  // 添加max_size参数
+    options: &ScanOptions,  // 过滤条件和扫描选项
     current_depth: usize,  // 当前深度
-    max_depth: usize,      // 最大深度，0表示不限制
-    pattern: Option<&Regex>,  // 添加正则表达式参数
 ) -> Option<TreeNode> {
     let dir_path = dir_path.as_ref();
     
     // 检查深度限制
-    if max_depth > 0 && current_depth > max_depth {
+    if options.beyond_depth(current_depth) {
         return None;
     }
+
+    let entries: Vec<_> = match fs::read_dir(dir_path) {
+        Ok(entries) => entries.filter_map(Result::ok).collect(),
+        Err(err) => {
+            options.report_error(dir_path, &err);
+            return None;
+        }
+    };
+    let (files, special, dirs) = process_directory_entries(entries, options);
+
+    // 将压缩包展开为虚拟目录
+    let (files, archive_nodes) = if options.archives {
+        archive::expand_archives(files, options, current_depth + 1)
+    } else {
+        (files, Vec::new())
+    };
+
+    // Create a directory node
+    let mut dir_node = TreeNode::new_directory(dir_path.to_path_buf());
     
-    match fs::read_dir(dir_path) {
-        Ok(entries) => {
-            let entries: Vec<_> = entries.filter_map(Result::ok).collect();
-            let (files, dirs) = process_directory_entries(entries, ext, ignore_dirs, min_size, max_size, pattern);
-            
-            // Create a directory node
-            let mut dir_node = TreeNode::new_directory(dir_path.to_path_buf());
-            
-            // 先统计当前目录的直接文件
-            let direct_files = files.len();
-            let direct_size: u64 = files.iter().map(|(_, size)| *size).sum();
-            
-            // 初始化total等于direct的值
-            let mut total_files = direct_files;
-            let mut total_size = direct_size;
-            
-            // Process files
-            for (file_path, file_size) in files {
-                if let TreeNode::Directory { files, .. } = &mut dir_node {
-                    files.push(TreeNode::new_file(file_path, file_size));
-                }
-            }
+    // 先统计当前目录的直接文件，特殊文件不计入
+    let direct_files = files.len();
+    let direct_size: u64 = files.iter().map(|(_, size)| *size).sum();
+    
+    // 初始化total等于direct的值
+    let mut total_files = direct_files;
+    let mut total_size = direct_size;
+    
+    // Process files
+    if let TreeNode::Directory { files: file_list, .. } = &mut dir_node {
+        *file_list = file_nodes(files, special);
+    }
+    
+    // Process subdirectories
+    for subdir_path in dirs {
+        // 递归时增加深度计数
+        if let Some(subdir_node) = build_directory_tree(subdir_path, options, current_depth + 1) {
+            // Only add directories that have files (directly or in subdirs)
+            let has_files = match &subdir_node {
+                TreeNode::Directory { total_files, .. } => *total_files > 0,
+                _ => false,
+            };
             
-            // Process subdirectories
-            for subdir_path in dirs {
-                // 递归时增加深度计数
-                if let Some(subdir_node) = build_directory_tree(
-                    subdir_path, ext, ignore_dirs, min_size, max_size, current_depth + 1, max_depth, pattern
-                ) {
-                    // Only add directories that have files (directly or in subdirs)
-                    let has_files = match &subdir_node {
-                        TreeNode::Directory { total_files, .. } => *total_files > 0,
-                        _ => false,
-                    };
-                    
-                    if has_files {
-                        if let TreeNode::Directory { dirs, .. } = &mut dir_node {
-                            // Update total counts by adding subdir values
-                            if let TreeNode::Directory { total_files: subdir_files, total_size: subdir_size, .. } = &subdir_node {
-                                total_files += subdir_files;
-                                total_size += subdir_size;
-                            }
-                            dirs.push(subdir_node);
-                        }
+            if has_files {
+                if let TreeNode::Directory { dirs, .. } = &mut dir_node {
+                    // Update total counts by adding subdir values
+                    if let TreeNode::Directory { total_files: subdir_files, total_size: subdir_size, .. } = &subdir_node {
+                        total_files += subdir_files;
+                        total_size += subdir_size;
                     }
+                    dirs.push(subdir_node);
                 }
             }
-            
-            // Update directory stats
-            if let TreeNode::Directory { 
-                total_files: ref mut tf, 
-                total_size: ref mut ts, 
-                direct_files: ref mut df, 
-                direct_size: ref mut ds, 
-                .. 
-            } = dir_node {
-                *tf = total_files;
-                *ts = total_size;
-                *df = direct_files;
-                *ds = direct_size;
-            }
-            
-            // Only return directory if it has files (directly or in subdirs)
-            match &dir_node {
-                TreeNode::Directory { total_files, .. } if *total_files > 0 => Some(dir_node),
-                _ => None,
-            }
-        },
-        Err(_) => {
-            eprintln!("Error accessing directory: {}", dir_path.display());
-            None
         }
     }
-}
 
-/// 主要的文件列表处理函数
-pub fn list_files<P: AsRef<Path>>(
-    indir: P,
-    prefix: &str,
-    is_last_items: &[bool],
-    _root: &Path,  // 添加下划线前缀表明这是一个有意未使用的变量
-    ext: &str,
-    ignore_dirs: &[String],
-    stats: Arc<Mutex<FileStats>>,
-    min_size: u64,
-    max_size: u64,  // 添加max_size参数
-    include_children: bool,
-    show_stats_only: bool, // 新增参数
-    max_depth: usize,  // 添加max_depth参数
-    pattern: Option<&Regex>,  // 添加正则表达式参数
-) {
-    // 从深度1开始构建树结构
-    if let Some(tree) = build_directory_tree(indir, ext, ignore_dirs, min_size, max_size, 1, max_depth, pattern) {
-        if !show_stats_only {  // 修复括号错误
-            // 使用print_tree_file打印完整的文件树结构
-            let mut local_stats = FileStats {
-                total_files: 0,
-                total_dirs: 0,
-                total_bytes: 0,
-            };
-            
-            print_tree_file(&tree, prefix, is_last_items, &mut local_stats);
-            
-            // Update the global stats
-            let mut stats_guard = stats.lock().unwrap();
-            stats_guard.total_files += local_stats.total_files;
-            stats_guard.total_dirs += local_stats.total_dirs;
-            stats_guard.total_bytes += local_stats.total_bytes;
-        } else {
-            // 使用print_tree_num打印目录统计信息
-            let mut local_stats = FileStats {
-                total_files: 0,
-                total_dirs: 0,
-                total_bytes: 0,
-            };
-            
-            print_tree_num(&tree, prefix, is_last_items, &mut local_stats, include_children);
-            
-            // Update the global stats
-            let mut stats_guard = stats.lock().unwrap();
-            stats_guard.total_files += local_stats.total_files;
-            stats_guard.total_dirs += local_stats.total_dirs;
-            stats_guard.total_bytes += local_stats.total_bytes;
+    // 压缩包与子目录一起按名称排序
+    if let TreeNode::Directory { dirs, .. } = &mut dir_node {
+        if !archive_nodes.is_empty() {
+            for archive_node in archive_nodes {
+                // 上级目录的大小计入压缩包在磁盘上的大小，而不是解压后的大小
+                if let TreeNode::Directory { total_files: archive_files, .. } = &archive_node {
+                    total_files += archive_files;
+                    total_size += archive_node.disk_size();
+                }
+                dirs.push(archive_node);
+            }
+            dirs.sort_by(|a, b| a.name().cmp(b.name()));
         }
     }
+    
+    // Update directory stats
+    if let TreeNode::Directory { 
+        total_files: ref mut tf, 
+        total_size: ref mut ts, 
+        direct_files: ref mut df, 
+        direct_size: ref mut ds, 
+        .. 
+    } = dir_node {
+        *tf = total_files;
+        *ts = total_size;
+        *df = direct_files;
+        *ds = direct_size;
+    }
+    
+    // Only return directory if it has files (directly or in subdirs)
+    match &dir_node {
+        TreeNode::Directory { total_files, .. } if *total_files > 0 => Some(dir_node),
+        _ => None,
+    }
+}
+
+/// 主要的文件列表处理函数，将已构建（或从快照加载）的树结构写入 `out`
+pub fn list_files(tree: &TreeNode, options: &DisplayOptions, out: &mut dyn Write) -> io::Result<()> {
+    formatter_for(options).write_tree(tree, out)
 }
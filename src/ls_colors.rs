@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 未设置 LS_COLORS 时使用的配色，与 GNU dircolors 的默认值一致
+const DEFAULT_KINDS: &str = "di=01;34:ln=01;36:pi=40;33:so=01;35:do=01;35:bd=40;33;01:cd=40;33;01:\
+or=40;31;01:su=37;41:sg=30;43:tw=30;42:ow=34;42:st=37;44:ex=01;32";
+
+/// 未设置 LS_COLORS 时按扩展名使用的配色：压缩包红色，图片和视频品红，音频青色
+const DEFAULT_EXTENSIONS: &str = "*.tar=01;31:*.tgz=01;31:*.tzst=01;31:*.zip=01;31:*.gz=01;31:\
+*.bz2=01;31:*.xz=01;31:*.zst=01;31:*.lz4=01;31:*.7z=01;31:*.rar=01;31:*.jar=01;31:*.deb=01;31:\
+*.rpm=01;31:*.jpg=01;35:*.jpeg=01;35:*.png=01;35:*.gif=01;35:*.bmp=01;35:*.tif=01;35:\
+*.tiff=01;35:*.svg=01;35:*.webp=01;35:*.ico=01;35:*.mp4=01;35:*.mkv=01;35:*.webm=01;35:\
+*.mov=01;35:*.avi=01;35:*.mp3=00;36:*.flac=00;36:*.ogg=00;36:*.wav=00;36:*.m4a=00;36:*.aac=00;36";
+
+/// LS_COLORS 中的配色：按类型（`di`、`ex` 等两字母代码）和按文件名后缀（`*.tar`）
+pub struct LsColors {
+    kinds: HashMap<String, String>,
+    /// 小写的后缀和配色，最长的匹配优先
+    suffixes: Vec<(String, String)>,
+}
+
+impl LsColors {
+    /// 解析 `di=01;34:*.tar=01;31` 格式的配置，在已有配色的基础上覆盖
+    fn parse_into(&mut self, spec: &str) {
+        for entry in spec.split(':') {
+            let Some((key, code)) = entry.split_once('=') else {
+                continue;
+            };
+            // 只接受 SGR 参数，`ln=target` 表示使用链接目标的配色
+            if code != "target" && !code.chars().all(|c| c.is_ascii_digit() || c == ';') {
+                continue;
+            }
+            match key.strip_prefix('*') {
+                Some(suffix) => self.suffixes.push((suffix.to_lowercase(), code.to_string())),
+                None => {
+                    self.kinds.insert(key.to_string(), code.to_string());
+                }
+            }
+        }
+    }
+
+    /// 解析配置，未指定的类型使用默认配色
+    pub fn parse(spec: &str) -> LsColors {
+        let mut colors = LsColors { kinds: HashMap::new(), suffixes: Vec::new() };
+        colors.parse_into(DEFAULT_KINDS);
+        colors.parse_into(spec);
+        colors
+    }
+
+    /// 读取 LS_COLORS 环境变量，未设置时使用内置的默认配色
+    pub fn from_env() -> LsColors {
+        match std::env::var("LS_COLORS") {
+            Ok(spec) => LsColors::parse(&spec),
+            Err(_) => LsColors::parse(DEFAULT_EXTENSIONS),
+        }
+    }
+
+    fn kind(&self, key: &str) -> Option<&str> {
+        self.kinds.get(key).map(String::as_str)
+    }
+
+    /// 按文件名后缀查找配色
+    fn suffix(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.suffixes
+            .iter()
+            .filter(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .max_by_key(|(suffix, _)| suffix.len())
+            .map(|(_, code)| code.as_str())
+    }
+
+    /// 目录的配色；粘滞位和其他用户可写的目录与 `ls` 一样单独着色
+    pub fn dir_style(&self, path: &Path) -> Option<&str> {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return self.kind("di");
+        };
+        if metadata.file_type().is_symlink() {
+            return self.link_style(path);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = metadata.permissions().mode();
+            let key = match (mode & 0o1000 != 0, mode & 0o002 != 0) {
+                (true, true) => "tw",
+                (false, true) => "ow",
+                (true, false) => "st",
+                (false, false) => "di",
+            };
+            if let Some(code) = self.kind(key) {
+                return Some(code);
+            }
+        }
+        self.kind("di")
+    }
+
+    /// 文件的配色：依次按类型、权限和后缀判断；路径不存在时（快照、压缩包内的文件）只看后缀
+    pub fn file_style(&self, path: &Path) -> Option<&str> {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return self.suffix(name).or_else(|| self.kind("fi"));
+        };
+        let file_type = metadata.file_type();
+        if file_type.is_symlink() {
+            return self.link_style(path);
+        }
+        if let Some(key) = special_kind(&file_type) {
+            return self.kind(key);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = metadata.permissions().mode();
+            let key = if mode & 0o4000 != 0 {
+                Some("su")
+            } else if mode & 0o2000 != 0 {
+                Some("sg")
+            } else if mode & 0o111 != 0 {
+                Some("ex")
+            } else {
+                None
+            };
+            if let Some(code) = key.and_then(|key| self.kind(key)) {
+                return Some(code);
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            if name.to_lowercase().ends_with(".exe") {
+                if let Some(code) = self.kind("ex") {
+                    return Some(code);
+                }
+            }
+        }
+        self.suffix(name).or_else(|| self.kind("fi"))
+    }
+
+    /// 符号链接的配色；目标不存在时使用 `or`，`ln=target` 时使用目标的配色
+    fn link_style(&self, path: &Path) -> Option<&str> {
+        match fs::metadata(path) {
+            Err(_) => self.kind("or").or_else(|| self.kind("ln")),
+            Ok(_) if self.kind("ln") == Some("target") => {
+                let target = fs::canonicalize(path).ok()?;
+                if target.is_dir() {
+                    self.dir_style(&target)
+                } else {
+                    self.file_style(&target)
+                }
+            }
+            Ok(_) => self.kind("ln"),
+        }
+    }
+}
+
+/// 管道、套接字和设备文件在 LS_COLORS 中的代码
+#[cfg(unix)]
+fn special_kind(file_type: &fs::FileType) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_fifo() {
+        Some("pi")
+    } else if file_type.is_socket() {
+        Some("so")
+    } else if file_type.is_block_device() {
+        Some("bd")
+    } else if file_type.is_char_device() {
+        Some("cd")
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special_kind(_file_type: &fs::FileType) -> Option<&'static str> {
+    None
+}
+
+/// 进程中共用的配色，第一次使用时读取 LS_COLORS
+pub fn ls_colors() -> &'static LsColors {
+    static COLORS: OnceLock<LsColors> = OnceLock::new();
+    COLORS.get_or_init(LsColors::from_env)
+}
+
+/// 用 SGR 参数为文本着色；关闭颜色或没有配色时原样返回
+pub fn paint(text: &str, style: Option<&str>) -> String {
+    // `0` 和 `00` 表示不着色
+    let style = style.filter(|code| !code.trim_matches(['0', ';']).is_empty());
+    match style {
+        Some(code) if colored::control::SHOULD_COLORIZE.should_colorize() => format!("\x1b[{}m{}\x1b[0m", code, text),
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_kinds_and_suffixes() {
+        let colors = LsColors::parse("di=01;33:*.tar=01;31:*.TAR.GZ=00;35:ex=bogus");
+        assert_eq!(colors.kind("di"), Some("01;33"));
+        // 未指定的类型使用默认配色，格式错误的条目被忽略
+        assert_eq!(colors.kind("ln"), Some("01;36"));
+        assert_eq!(colors.kind("ex"), Some("01;32"));
+        assert_eq!(colors.suffix("backup.tar"), Some("01;31"));
+        // 后缀不区分大小写，最长的匹配优先
+        assert_eq!(colors.suffix("data.tar.gz"), Some("00;35"));
+        assert_eq!(colors.suffix("notes.txt"), None);
+    }
+
+    #[test]
+    fn accepts_link_target_style() {
+        let colors = LsColors::parse("ln=target");
+        assert_eq!(colors.kind("ln"), Some("target"));
+    }
+}
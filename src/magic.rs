@@ -0,0 +1,258 @@
+use rayon::prelude::*;
+use regex::Regex;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::tree::TreeNode;
+
+/// 识别文件类型时读取的字节数（tar 的标识位于偏移 257 处）
+const MAGIC_SIZE: usize = 512;
+
+/// 根据开头的字节识别 MIME 类型
+pub fn detect_mime(header: &[u8]) -> &'static str {
+    let starts = |magic: &[u8]| header.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+    // 小端序的 32 位整数，超出读取范围时为 None
+    let u32_at = |offset: usize| {
+        header
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    };
+
+    if header.is_empty() {
+        "application/x-empty"
+    } else if starts(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if starts(b"\xff\xd8\xff") {
+        "image/jpeg"
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        "image/gif"
+    } else if starts(b"RIFF") && at(8, b"WEBP") {
+        "image/webp"
+    } else if starts(b"BM") && matches!(u32_at(14), Some(12 | 40 | 56 | 108 | 124)) {
+        // 以 BM 开头的文本很常见，再检查 DIB 头的长度
+        "image/bmp"
+    } else if starts(b"II*\0") || starts(b"MM\0*") {
+        "image/tiff"
+    } else if starts(b"\0\0\x01\0") {
+        "image/x-icon"
+    } else if starts(b"%PDF-") {
+        "application/pdf"
+    } else if starts(b"PK\x03\x04") || starts(b"PK\x05\x06") {
+        "application/zip"
+    } else if starts(b"\x1f\x8b") {
+        "application/gzip"
+    } else if starts(b"BZh") {
+        "application/x-bzip2"
+    } else if starts(b"\xfd7zXZ\0") {
+        "application/x-xz"
+    } else if starts(b"\x28\xb5\x2f\xfd") {
+        "application/zstd"
+    } else if starts(b"7z\xbc\xaf\x27\x1c") {
+        "application/x-7z-compressed"
+    } else if starts(b"Rar!\x1a\x07") {
+        "application/vnd.rar"
+    } else if at(257, b"ustar") {
+        "application/x-tar"
+    } else if starts(b"\x7fELF") {
+        "application/x-executable"
+    } else if starts(b"MZ") && u32_at(0x3c).is_some_and(|offset| at(offset, b"PE\0\0")) {
+        // e_lfanew 指向的 PE 签名
+        "application/x-msdownload"
+    } else if starts(b"\xcf\xfa\xed\xfe") || starts(b"\xce\xfa\xed\xfe") {
+        "application/x-mach-binary"
+    } else if starts(b"\0asm") {
+        "application/wasm"
+    } else if starts(b"SQLite format 3\0") {
+        "application/vnd.sqlite3"
+    } else if starts(b"ID3") || starts(b"\xff\xfb") || starts(b"\xff\xf3") {
+        "audio/mpeg"
+    } else if starts(b"fLaC") {
+        "audio/flac"
+    } else if starts(b"OggS") {
+        "audio/ogg"
+    } else if starts(b"RIFF") && at(8, b"WAVE") {
+        "audio/wav"
+    } else if at(4, b"ftyp") {
+        ftyp_mime(header.get(8..12).unwrap_or_default())
+    } else if starts(b"\x1a\x45\xdf\xa3") {
+        "video/webm"
+    } else if is_text(header) {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// ISO 媒体文件（`ftyp`）按主品牌区分图片、音频和视频
+fn ftyp_mime(brand: &[u8]) -> &'static str {
+    match brand {
+        b"heic" | b"heix" | b"heim" | b"heis" => "image/heic",
+        b"mif1" | b"msf1" => "image/heif",
+        b"avif" | b"avis" => "image/avif",
+        b"M4A " | b"M4B " | b"M4P " => "audio/mp4",
+        b"qt  " => "video/quicktime",
+        b"3gp4" | b"3gp5" | b"3gp6" => "video/3gpp",
+        _ => "video/mp4",
+    }
+}
+
+/// 没有 NUL 且是合法 UTF-8（允许末尾被截断的字符）时视为文本
+fn is_text(header: &[u8]) -> bool {
+    if header.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(header) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none() && header.len() == MAGIC_SIZE,
+    }
+}
+
+/// 读取文件开头的字节并识别类型
+pub fn detect_file(path: &Path) -> io::Result<&'static str> {
+    // 特殊文件与 `file --mime-type` 一样报告为 inode/*，不读取内容
+    if let Some(mime) = special_mime(&fs::metadata(path)?.file_type()) {
+        return Ok(mime);
+    }
+    let mut header = Vec::with_capacity(MAGIC_SIZE);
+    File::open(path)?.take(MAGIC_SIZE as u64).read_to_end(&mut header)?;
+    Ok(detect_mime(&header))
+}
+
+/// 管道、套接字和设备文件的类型
+#[cfg(unix)]
+fn special_mime(file_type: &fs::FileType) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_fifo() {
+        Some("inode/fifo")
+    } else if file_type.is_socket() {
+        Some("inode/socket")
+    } else if file_type.is_block_device() {
+        Some("inode/blockdevice")
+    } else if file_type.is_char_device() {
+        Some("inode/chardevice")
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special_mime(_file_type: &fs::FileType) -> Option<&'static str> {
+    None
+}
+
+/// 将 `image/*` 这样的通配模式转换为正则表达式
+pub fn mime_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    let escaped = regex::escape(pattern).replace(r"\*", ".*");
+    Regex::new(&format!("^{}$", escaped))
+}
+
+/// 并行识别树中每个文件的类型；指定过滤条件时移除不匹配的文件
+pub fn classify_tree(tree: &mut TreeNode, filter: Option<&Regex>) {
+    tree.files_mut().par_iter_mut().for_each(|node| {
+        if let TreeNode::File { path, mime, .. } = &mut **node {
+            *mime = detect_file(path).ok().map(str::to_string);
+        }
+    });
+
+    if let Some(filter) = filter {
+        tree.retain_files(&|node| match node {
+            TreeNode::File { mime: Some(mime), .. } => filter.is_match(mime),
+            _ => false,
+        });
+    }
+}
+
+/// 按类型汇总文件数和大小，按大小从大到小排序；特殊文件与文件总数一样不计入
+pub fn by_type(tree: &TreeNode) -> Vec<(String, usize, u64)> {
+    fn visit(node: &TreeNode, out: &mut HashMap<String, (usize, u64)>) {
+        match node {
+            TreeNode::File { special: true, .. } => {}
+            TreeNode::File { size, mime: Some(mime), .. } => {
+                let entry = out.entry(mime.clone()).or_default();
+                entry.0 += 1;
+                entry.1 += size;
+            }
+            TreeNode::File { .. } => {}
+            TreeNode::Directory { files, dirs, .. } => {
+                for child in files.iter().chain(dirs.iter()) {
+                    visit(child, out);
+                }
+            }
+        }
+    }
+    let mut out = HashMap::new();
+    visit(tree, &mut out);
+    let mut types: Vec<_> = out
+        .into_iter()
+        .map(|(mime, (files, size))| (mime, files, size))
+        .collect();
+    types.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    types
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_common_signatures() {
+        assert_eq!(detect_mime(b""), "application/x-empty");
+        assert_eq!(detect_mime(b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(detect_mime(b"%PDF-1.7"), "application/pdf");
+        assert_eq!(detect_mime(b"PK\x03\x04rest"), "application/zip");
+        assert_eq!(detect_mime(b"\x7fELF\x02\x01"), "application/x-executable");
+        assert_eq!(detect_mime(b"plain text\n"), "text/plain");
+        assert_eq!(detect_mime(b"BMW list of cars, one per line\n"), "text/plain");
+        assert_eq!(detect_mime(b"MZ is a prefix of this text file\n"), "text/plain");
+        assert_eq!(detect_mime(b"\x00\x01\x02\x03"), "application/octet-stream");
+    }
+
+    #[test]
+    fn checks_headers_behind_short_signatures() {
+        let mut bmp = b"BM\x36\0\0\0\0\0\0\0\x36\0\0\0".to_vec();
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        assert_eq!(detect_mime(&bmp), "image/bmp");
+
+        let mut exe = vec![0; 0x80];
+        exe[..2].copy_from_slice(b"MZ");
+        exe[0x3c] = 0x40;
+        exe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        assert_eq!(detect_mime(&exe), "application/x-msdownload");
+        exe[0x40] = b'X';
+        assert_eq!(detect_mime(&exe), "application/octet-stream");
+    }
+
+    #[test]
+    fn detects_ftyp_brands() {
+        let ftyp = |brand: &[u8]| [b"\0\0\0\x18ftyp", brand, b"\0\0\0\0"].concat();
+        assert_eq!(detect_mime(&ftyp(b"isom")), "video/mp4");
+        assert_eq!(detect_mime(&ftyp(b"heic")), "image/heic");
+        assert_eq!(detect_mime(&ftyp(b"avif")), "image/avif");
+        assert_eq!(detect_mime(&ftyp(b"M4A ")), "audio/mp4");
+        assert_eq!(detect_mime(&ftyp(b"qt  ")), "video/quicktime");
+    }
+
+    #[test]
+    fn tar_header_at_offset() {
+        let mut header = vec![b'a'; MAGIC_SIZE];
+        header[257..262].copy_from_slice(b"ustar");
+        header[0] = 0;
+        assert_eq!(detect_mime(&header), "application/x-tar");
+    }
+
+    #[test]
+    fn mime_patterns_match_whole_types() {
+        let images = mime_pattern("image/*").unwrap();
+        assert!(images.is_match("image/png"));
+        assert!(!images.is_match("video/mp4"));
+        assert!(!images.is_match("xmage/png"));
+
+        let pdf = mime_pattern("application/pdf").unwrap();
+        assert!(pdf.is_match("application/pdf"));
+        assert!(!pdf.is_match("application/pdfx"));
+    }
+}
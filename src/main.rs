@@ -1,17 +1,19 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use colored::*;
-use regex::Regex;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-mod file_size;
-mod list_files;
-mod print;
-mod tree;
+use ntree::{
+    contains, diff, dupes, file_size, git, hash, interactive, lines, magic, print, snapshot, tree, trend,
+};
+#[cfg(target_os = "linux")]
+use ntree::watch;
+use ntree::list_files::list_files;
+use ntree::{FileStats, Scanner};
 
-use file_size::{format_size, parse_size};
-use list_files::{list_files, FileStats};
+use file_size::{format_size, format_size_delta, parse_size};
 
 /// Command line arguments structure
 #[derive(Parser)]
@@ -49,9 +51,204 @@ struct Cli {
     #[arg(short = 'L', long = "level", default_value = "0")]
     max_depth: usize,
 
+    /// Include hidden files and directories (names starting with a dot)
+    #[arg(short = 'a', long = "all")]
+    all: bool,
+
     /// Filter files by pattern (supports glob patterns like *.rs)
     #[arg(short = 'p', long = "pattern")]
     pattern: Option<String>,
+
+    /// Show each directory's share of its parent and of the root as a bar
+    #[arg(short = 'b', long = "bars")]
+    show_bars: bool,
+
+    /// Show only the largest N files of each directory, 0 means unlimited
+    #[arg(long = "max-files-per-dir", value_name = "N", default_value = "0")]
+    max_files_per_dir: usize,
+
+    /// Collapse directories smaller than specified size into a single line
+    #[arg(long = "collapse-size", value_name = "SIZE")]
+    collapse_size: Option<String>,
+
+    /// Collapse directories with fewer than N files into a single line
+    #[arg(long = "collapse-files", value_name = "N", default_value = "0")]
+    collapse_files: usize,
+
+    /// In -n mode, fold subdirectories below PCT% of their parent's size into an <other> entry
+    #[arg(long = "min-share", value_name = "PCT", default_value = "0")]
+    min_share: f64,
+
+    /// Browse the scanned tree interactively (arrow keys to navigate, q to quit)
+    #[arg(short = 'i', long = "interactive")]
+    interactive: bool,
+
+    /// In interactive mode, move deleted items into this directory instead of removing them
+    #[arg(long = "trash-dir", value_name = "DIR", conflicts_with = "load")]
+    trash_dir: Option<PathBuf>,
+
+    /// Save the complete scan to a compressed snapshot file
+    #[arg(long = "save", value_name = "FILE")]
+    save: Option<PathBuf>,
+
+    /// Render a previously saved snapshot instead of scanning the filesystem
+    #[arg(long = "load", value_name = "FILE", conflicts_with = "save")]
+    load: Option<PathBuf>,
+
+    /// Compare against an older scan (a directory or a saved snapshot) and show what changed
+    #[arg(long = "diff", value_name = "BASE")]
+    diff: Option<PathBuf>,
+
+    /// Show the fastest-growing directories across the snapshots saved in DIR
+    #[arg(long = "trend", value_name = "DIR")]
+    trend: Option<PathBuf>,
+
+    /// Number of entries listed in trend and duplicate reports, 0 means all
+    #[arg(long = "top", value_name = "N", default_value = "10")]
+    top: usize,
+
+    /// Keep watching for changes after the scan (Linux only); redraws the view in -n mode
+    #[arg(long = "watch", conflicts_with_all = ["load", "interactive", "diff"])]
+    watch: bool,
+
+    /// Find duplicate files by size and content hash, and report the wasted space
+    #[arg(long = "dupes", conflicts_with_all = ["interactive", "diff", "watch"])]
+    dupes: bool,
+
+    /// Compute a content hash for each file and show it next to the size
+    #[arg(long = "hash", value_name = "ALGO")]
+    hash: Option<HashArg>,
+
+    /// Write a sha256sum-compatible manifest of file hashes (implies --hash sha256 if not set)
+    #[arg(long = "manifest", value_name = "FILE", conflicts_with = "load")]
+    manifest: Option<PathBuf>,
+
+    /// Re-scan and report mismatched, missing and extra files against a manifest
+    #[arg(long = "verify", value_name = "MANIFEST", conflicts_with_all = ["load", "manifest", "interactive", "diff", "watch", "dupes"])]
+    verify: Option<PathBuf>,
+
+    /// Count lines of text files (code, comment and blank by language) and aggregate them per directory
+    #[arg(long = "lines")]
+    count_lines: bool,
+
+    /// Detect file types from their leading bytes and show a breakdown in the summary
+    #[arg(long = "types")]
+    show_types: bool,
+
+    /// Keep only files whose detected type matches (e.g. image/*, application/pdf); implies --types
+    #[arg(long = "mime", value_name = "TYPE")]
+    mime: Option<String>,
+
+    /// Count only text files whose content matches the regular expression
+    #[arg(long = "contains", value_name = "REGEX")]
+    contains: Option<String>,
+
+    /// With --contains, read at most SIZE bytes of each file (default 16MB)
+    #[arg(long = "max-read", value_name = "SIZE", requires = "contains")]
+    max_read: Option<String>,
+
+    /// Expand .zip, .tar, .tar.gz and .tar.zst files into virtual directories of their contents
+    #[arg(long = "archives", conflicts_with_all = ["hash", "manifest", "verify", "dupes", "contains"])]
+    archives: bool,
+
+    /// Annotate files with their git status (staged, modified, untracked, ignored) and count them per directory
+    #[arg(long = "git-status")]
+    git_status: bool,
+
+    /// Keep only files with the given git status; implies --git-status
+    #[arg(long = "git", value_name = "STATUS", conflicts_with = "load")]
+    git: Option<GitArg>,
+
+    /// In -n mode, show each directory's number of commits, distinct authors and last change from git history
+    #[arg(long = "git-history", conflicts_with = "load")]
+    git_history: bool,
+
+    /// Prefix entries with file-type icons (requires a Nerd Font)
+    #[arg(long = "icons")]
+    icons: bool,
+
+    /// Write the tree or report and its summary to FILE instead of standard output
+    #[arg(short = 'o', long = "output", value_name = "FILE", conflicts_with_all = ["interactive", "watch"])]
+    output: Option<PathBuf>,
+
+    /// When to color the output: auto (only on a terminal and without NO_COLOR), always or never
+    #[arg(long = "color", value_name = "WHEN", default_value = "auto")]
+    color: ColorMode,
+
+    /// Suppress the informational header and the summary; only the results are printed
+    #[arg(short = 'q', long = "quiet")]
+    quiet: bool,
+
+    /// Print only the totals, one `key=value` per line, instead of the tree
+    #[arg(long = "summary-only", conflicts_with_all = ["interactive", "watch", "trend", "diff", "dupes", "verify"])]
+    summary_only: bool,
+}
+
+/// 何时输出 ANSI 颜色；Auto 只在输出到终端且未设置 NO_COLOR 时着色
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// 设置全局的颜色开关，`is_terminal` 表示输出目标是否为终端
+    fn apply(self, is_terminal: bool) {
+        let enabled = match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                is_terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        };
+        colored::control::set_override(enabled);
+    }
+}
+
+/// --hash 的取值，对应 `hash::HashAlgorithm`
+#[derive(Clone, Copy, ValueEnum)]
+enum HashArg {
+    Blake3,
+    Sha256,
+}
+
+impl From<HashArg> for hash::HashAlgorithm {
+    fn from(arg: HashArg) -> Self {
+        match arg {
+            HashArg::Blake3 => hash::HashAlgorithm::Blake3,
+            HashArg::Sha256 => hash::HashAlgorithm::Sha256,
+        }
+    }
+}
+
+/// --git 的取值，对应 `git::GitState`
+#[derive(Clone, Copy, ValueEnum)]
+enum GitArg {
+    Staged,
+    Modified,
+    Untracked,
+    Ignored,
+}
+
+impl From<GitArg> for git::GitState {
+    fn from(arg: GitArg) -> Self {
+        match arg {
+            GitArg::Staged => git::GitState::Staged,
+            GitArg::Modified => git::GitState::Modified,
+            GitArg::Untracked => git::GitState::Untracked,
+            GitArg::Ignored => git::GitState::Ignored,
+        }
+    }
+}
+
+/// 提示信息输出到标准错误，避免混入管道中的结果；--quiet 时不输出
+macro_rules! note {
+    ($quiet:expr, $($arg:tt)*) => {
+        if !$quiet {
+            eprintln!($($arg)*);
+        }
+    };
 }
 
 fn main() {
@@ -61,6 +258,52 @@ fn main() {
     // Use clap to parse command line arguments
     let args = Cli::parse();
 
+    // 打开输出目标，默认根据输出目标是否为终端决定是否着色
+    let (mut out, is_terminal): (Box<dyn Write>, bool) = match &args.output {
+        Some(output_path) => match File::create(output_path) {
+            Ok(file) => {
+                let is_terminal = file.is_terminal();
+                (Box::new(BufWriter::new(file)), is_terminal)
+            }
+            Err(err) => {
+                eprintln!("Error creating output file {}: {}", output_path.display(), err);
+                return;
+            }
+        },
+        None => (Box::new(BufWriter::new(io::stdout())), io::stdout().is_terminal()),
+    };
+    args.color.apply(is_terminal);
+    let quiet = args.quiet;
+    let git_state = args.git.map(git::GitState::from);
+
+    // 趋势模式只读取已保存的快照，不扫描文件系统
+    if let Some(trend_dir) = &args.trend {
+        let snapshots = match trend::load_snapshots(trend_dir) {
+            Ok((snapshots, skipped)) => {
+                for snapshot in skipped {
+                    match snapshot.reason {
+                        trend::SkipReason::Unreadable(err) => {
+                            eprintln!("Skipping {}: {}", snapshot.path.display(), err)
+                        }
+                        trend::SkipReason::DifferentScan => eprintln!(
+                            "Skipping {}: different directory or filters than the latest snapshot",
+                            snapshot.path.display()
+                        ),
+                    }
+                }
+                snapshots
+            }
+            Err(err) => {
+                eprintln!("Error reading snapshots from {}: {}", trend_dir.display(), err);
+                return;
+            }
+        };
+        let result = trend::write_trend(&snapshots, args.top, &mut out);
+        finish_output(&mut out, result);
+        note!(quiet, "Time elapsed: {:.2?}", start_time.elapsed());
+        return;
+    }
+
     // Process arguments - 分离目录和模式
     let (directory, pattern_from_args) = process_paths(&args.paths);
     let dir_path = directory.to_string_lossy();
@@ -100,17 +343,25 @@ fn main() {
         u64::MAX // Maximum possible value
     };
 
+    // Handle collapse size threshold
+    let collapse_size = if let Some(size_str) = args.collapse_size {
+        match parse_size(&size_str) {
+            Ok(size) => size,
+            Err(err) => {
+                eprintln!("Error parsing size: {}", err);
+                return;
+            }
+        }
+    } else {
+        0
+    };
+
     // Handle pattern matching
     let pattern = if let Some(pattern_str) = &pattern_str {
         // Convert glob pattern to regex pattern
-        let regex_pattern = pattern_str
-            .replace(".", "\\.")  // Escape dots
-            .replace("*", ".*")   // Convert * to .*
-            .replace("?", ".");   // Convert ? to .
-
-        match Regex::new(&regex_pattern) {
+        match ntree::glob_to_regex(pattern_str) {
             Ok(re) => {
-                println!("Filtering by pattern: {}", pattern_str);
+                note!(quiet, "Filtering by pattern: {}", pattern_str);
                 Some(re)
             },
             Err(err) => {
@@ -122,78 +373,575 @@ fn main() {
         None
     };
 
-    println!("Counting files in directory: {}", dir_path.blue());
-    if !ext.is_empty() {
-        println!("Filtering by extension: {}", ext);
-    }
-    if !ignore_dirs.is_empty() {
-        println!("Ignoring directories: {}", ignore_dirs.join(", "));
-    }
-    if min_size > 0 {
-        println!("Filtering files smaller than: {}", format_size(min_size));
-    }
-    if max_size < u64::MAX {
-        println!("Filtering files larger than: {}", format_size(max_size));
+    // Handle content filter
+    let contains = if let Some(contains_str) = &args.contains {
+        let max_read = match args.max_read.as_deref().map(parse_size) {
+            Some(Ok(size)) => size,
+            Some(Err(err)) => {
+                eprintln!("Error parsing size: {}", err);
+                return;
+            }
+            None => contains::DEFAULT_MAX_READ,
+        };
+        match contains::ContentFilter::new(contains_str, max_read) {
+            Ok(filter) => Some(filter),
+            Err(err) => {
+                eprintln!("Error parsing content pattern: {}", err);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    // Handle content type filter
+    let mime_filter = if let Some(mime) = &args.mime {
+        match magic::mime_pattern(mime) {
+            Ok(re) => Some(re),
+            Err(err) => {
+                eprintln!("Error parsing mime type: {}", err);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(load_path) = &args.load {
+        note!(quiet, "Loading snapshot: {}", load_path.display().to_string().blue());
+    } else {
+        note!(quiet, "Counting files in directory: {}", dir_path.blue());
+        if !ext.is_empty() {
+            note!(quiet, "Filtering by extension: {}", ext);
+        }
+        if !ignore_dirs.is_empty() {
+            note!(quiet, "Ignoring directories: {}", ignore_dirs.join(", "));
+        }
+        if min_size > 0 {
+            note!(quiet, "Filtering files smaller than: {}", format_size(min_size));
+        }
+        if max_size < u64::MAX {
+            note!(quiet, "Filtering files larger than: {}", format_size(max_size));
+        }
+        if let Some(mime) = &args.mime {
+            note!(quiet, "Filtering by content type: {}", mime);
+        }
+        if let Some(filter) = &contains {
+            note!(
+                quiet,
+                "Filtering by content: {} (reading up to {} per file)",
+                filter.regex.as_str(),
+                format_size(filter.max_read)
+            );
+        }
+        if args.archives {
+            note!(quiet, "Expanding archives into their contents");
+        }
+        if let Some(state) = git_state {
+            note!(quiet, "Filtering by git status: {}", state.label());
+        }
     }
     if args.include_children {
-        println!("Including child directory files in count");
+        note!(quiet, "Including child directory files in count");
     }
     if args.show_stats_only {
-        println!("Showing directory statistics only (no file tree)");
+        note!(quiet, "Showing directory statistics only (no file tree)");
     }
     if args.max_depth > 0 {
-        println!("Maximum directory depth: {}", args.max_depth);
+        note!(quiet, "Maximum directory depth: {}", args.max_depth);
+    }
+    if args.max_files_per_dir > 0 {
+        note!(quiet, "Maximum files shown per directory: {}", args.max_files_per_dir);
     }
+    if collapse_size > 0 {
+        note!(quiet, "Collapsing directories smaller than: {}", format_size(collapse_size));
+    }
+    if args.collapse_files > 0 {
+        note!(quiet, "Collapsing directories with fewer than {} files", args.collapse_files);
+    }
+    if args.show_stats_only && args.min_share > 0.0 {
+        note!(quiet, "Folding directories below {}% of their parent into <other>", args.min_share);
+    }
+
+    let display = print::DisplayOptions {
+        include_children: args.include_children,
+        show_stats_only: args.show_stats_only,
+        show_bars: args.show_bars,
+        max_files_per_dir: args.max_files_per_dir,
+        collapse_size,
+        collapse_files: args.collapse_files,
+        min_share: args.min_share,
+        icons: args.icons,
+    };
+
+    // 使用相同的过滤条件扫描任意目录
+    let scanner_for = |root: &Path| {
+        let mut scanner = Scanner::new(root)
+            .ext(&ext)
+            .min_size(min_size)
+            .max_size(max_size)
+            .max_depth(args.max_depth)
+            .archives(args.archives)
+            .all(args.all)
+            .on_error(|path, err| eprintln!("Error accessing {}: {}", path.display(), err));
+        for dir_name in &ignore_dirs {
+            scanner = scanner.ignore(dir_name);
+        }
+        if let Some(pattern) = &pattern {
+            scanner = scanner.pattern(pattern.clone());
+        }
+        if let Some(filter) = &contains {
+            scanner = scanner.contains(filter.clone());
+        }
+        scanner
+    };
+
+    // 获取树结构：从快照加载，或扫描文件系统
+    let mut tree = if let Some(load_path) = &args.load {
+        match snapshot::load(load_path) {
+            Ok(snapshot) => {
+                let metadata = &snapshot.metadata;
+                note!(
+                    quiet,
+                    "Snapshot of {} taken on {} at {}",
+                    metadata.root.blue(),
+                    metadata.host,
+                    snapshot::format_timestamp(metadata.timestamp)
+                );
+                let filters = metadata.describe_filters();
+                if !filters.is_empty() {
+                    note!(quiet, "Snapshot filters: {}", filters.join("; "));
+                }
+                Some(snapshot.tree)
+            }
+            Err(err) => {
+                eprintln!("Error loading snapshot {}: {}", load_path.display(), err);
+                return;
+            }
+        }
+    } else {
+        let tree = match scanner_for(&directory).scan() {
+            Ok(tree) => tree,
+            Err(err) => {
+                eprintln!("Error accessing directory {}: {}", directory.display(), err);
+                std::process::exit(1);
+            }
+        };
+        // 没有匹配的文件时不显示树
+        Some(tree).filter(|tree| !matches!(tree, tree::TreeNode::Directory { total_files: 0, .. }))
+    };
+
+    // 计算内容哈希，校验和清单默认使用 SHA-256
+    let hash_algorithm = if args.manifest.is_some() || args.verify.is_some() {
+        Some(args.hash.map_or(hash::HashAlgorithm::Sha256, hash::HashAlgorithm::from))
+    } else {
+        args.hash.map(hash::HashAlgorithm::from)
+    };
+    if let (Some(algorithm), Some(tree), None) = (hash_algorithm, tree.as_mut(), &args.load) {
+        for (path, err) in hash::hash_tree(tree, algorithm) {
+            eprintln!("Error hashing {}: {}", path.display(), err);
+        }
+    }
+
+    // 根据文件内容识别类型，--mime 会移除不匹配的文件
+    if let (true, Some(scanned), None) = (args.show_types || mime_filter.is_some(), tree.as_mut(), &args.load) {
+        magic::classify_tree(scanned, mime_filter.as_ref());
+        if matches!(scanned, tree::TreeNode::Directory { total_files: 0, .. }) {
+            tree = None;
+        }
+    }
+
+    // 标注 git 状态，--git 会移除其他状态的文件
+    if let (true, Some(scanned), None) = (args.git_status || git_state.is_some(), tree.as_mut(), &args.load) {
+        if let Err(err) = git::annotate_tree(scanned, git_state) {
+            eprintln!("Error reading git status of {}: {}", directory.display(), err);
+            return;
+        }
+        if matches!(scanned, tree::TreeNode::Directory { total_files: 0, .. }) {
+            tree = None;
+        }
+    }
+
+    // 从 git 历史统计各目录的提交
+    if let (true, Some(scanned)) = (args.git_history, tree.as_mut()) {
+        if let Err(err) = git::annotate_history(scanned) {
+            eprintln!("Error reading git history of {}: {}", directory.display(), err);
+            return;
+        }
+    }
+
+    // 统计行数
+    if let (true, Some(tree), None) = (args.count_lines, tree.as_mut(), &args.load) {
+        lines::count_tree(tree);
+    }
+
+    // 校验模式：与清单比较
+    if let Some(manifest_path) = &args.verify {
+        let manifest = match hash::read_manifest(manifest_path) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                eprintln!("Error reading manifest {}: {}", manifest_path.display(), err);
+                return;
+            }
+        };
+        let report = hash::verify(tree.as_ref(), &manifest, manifest_path);
+        let (missing, skipped): (Vec<&String>, Vec<&String>) = report
+            .missing
+            .iter()
+            .partition(|file| !hash::exists_relative(&directory, file));
+
+        let result = write_verify(&mut out, &report, &missing, skipped.len(), !quiet);
+        finish_output(&mut out, result);
+        note!(quiet, "Time elapsed: {:.2?}", start_time.elapsed());
+        if !report.mismatched.is_empty() || !missing.is_empty() || !report.extra.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 写出校验和清单
+    if let Some(manifest_path) = &args.manifest {
+        if let Some(tree) = &tree {
+            match hash::write_manifest(manifest_path, tree) {
+                Ok(count) => note!(quiet, "Manifest with {} entries written to: {}", count, manifest_path.display()),
+                Err(err) => eprintln!("Error writing manifest {}: {}", manifest_path.display(), err),
+            }
+        }
+    }
+
+    // 保存快照，之后可以用 --load 离线查看
+    if let Some(save_path) = &args.save {
+        match tree.take() {
+            Some(scanned) => {
+                let snapshot = snapshot::Snapshot {
+                    metadata: snapshot::ScanMetadata::new(&scanner_for(&directory)),
+                    tree: scanned,
+                };
+                if let Err(err) = snapshot::save(save_path, &snapshot) {
+                    eprintln!("Error saving snapshot {}: {}", save_path.display(), err);
+                    return;
+                }
+                note!(quiet, "Snapshot saved to: {}", save_path.display());
+                tree = Some(snapshot.tree);
+            }
+            None => eprintln!("No matching files found, snapshot not saved"),
+        }
+    }
+
+    // 差异模式：与旧的目录或快照比较
+    if let Some(base_path) = &args.diff {
+        let base = if base_path.is_file() {
+            match snapshot::load(base_path) {
+                Ok(snapshot) => Some(snapshot.tree),
+                Err(err) => {
+                    eprintln!("Error loading snapshot {}: {}", base_path.display(), err);
+                    return;
+                }
+            }
+        } else {
+            match scanner_for(base_path).scan() {
+                Ok(base) => Some(base),
+                Err(err) => {
+                    eprintln!("Error accessing directory {}: {}", base_path.display(), err);
+                    std::process::exit(1);
+                }
+            }
+        };
+
+        note!(quiet, "Comparing against: {}\n", base_path.display().to_string().blue());
+        let result = diff::write_diff(base.as_ref(), tree.as_ref(), &mut out).and_then(|diff_stats| {
+            if quiet {
+                Ok(())
+            } else {
+                write_diff_summary(&mut out, &diff_stats)
+            }
+        });
+        finish_output(&mut out, result);
+        note!(quiet, "Time elapsed: {:.2?}", start_time.elapsed());
+        return;
+    }
+
+    // 重复文件模式：在扫描结果中查找内容相同的文件
+    if args.dupes {
+        match &tree {
+            Some(tree) => {
+                let result = dupes::write_duplicates(&dupes::find_duplicates(tree), args.top, !quiet, &mut out);
+                finish_output(&mut out, result);
+            }
+            None => note!(quiet, "No matching files found"),
+        }
+        note!(quiet, "Time elapsed: {:.2?}", start_time.elapsed());
+        return;
+    }
+
+    // 监听模式：初始扫描后持续更新统计
+    if args.watch {
+        #[cfg(target_os = "linux")]
+        {
+            let tree = tree.unwrap_or_else(|| tree::TreeNode::new_directory(directory.clone()));
+            let scanner = scanner_for(&directory);
+            let result = watch::watch(
+                tree,
+                scanner.options(),
+                |tree, events| {
+                    let (total_files, total_size) = match tree {
+                        tree::TreeNode::Directory { total_files, total_size, .. } => (*total_files, *total_size),
+                        tree::TreeNode::File { size, .. } => (1, *size),
+                    };
+
+                    if args.show_stats_only {
+                        // 重新绘制 -n 视图
+                        let _ = crossterm::execute!(
+                            std::io::stdout(),
+                            crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+                            crossterm::cursor::MoveTo(0, 0)
+                        );
+                        let options = print::DisplayOptions { show_stats_only: true, ..display.clone() };
+                        let _ = list_files(tree, &options, &mut io::stdout());
+                        println!(
+                            "\nWatching for changes (Ctrl+C to stop): {} files, {}",
+                            file_size::format_count(total_files).blue().bold(),
+                            format_size(total_size).green().bold()
+                        );
+                    } else if events.is_empty() {
+                        println!(
+                            "Watching {} for changes (Ctrl+C to stop): {} files, {}",
+                            tree.path().display().to_string().blue(),
+                            file_size::format_count(total_files).blue().bold(),
+                            format_size(total_size).green().bold()
+                        );
+                    } else {
+                        // 输出变化事件
+                        for event in events {
+                            let kind = match event.kind {
+                                watch::ChangeKind::Created => event.kind.label().green(),
+                                watch::ChangeKind::Deleted => event.kind.label().red(),
+                                watch::ChangeKind::Modified => event.kind.label().yellow(),
+                            };
+                            println!(
+                                "[{}] {:<8} {}",
+                                snapshot::format_timestamp(event.timestamp),
+                                kind,
+                                event.path.display()
+                            );
+                        }
+                        println!(
+                            "  total: {} files, {}",
+                            file_size::format_count(total_files).blue().bold(),
+                            format_size(total_size).green().bold()
+                        );
+                    }
+                },
+            );
+            if let Err(err) = result {
+                eprintln!("Error watching {}: {}", directory.display(), err);
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        eprintln!("--watch is only supported on Linux");
+        return;
+    }
+
+    // 交互模式：树结构只构建一次，然后交给浏览器
+    if args.interactive {
+        match tree {
+            Some(tree) => {
+                if let Err(err) = interactive::browse(tree, args.include_children, args.trash_dir, args.load.is_some()) {
+                    eprintln!("Error running interactive browser: {}", err);
+                }
+            }
+            None => note!(quiet, "No matching files found"),
+        }
+        return;
+    }
+
+    if args.summary_only {
+        let result = write_totals(&mut out, tree.as_ref());
+        finish_output(&mut out, result);
+    } else {
+        let result = write_output(&mut out, tree.as_ref(), &display, !quiet, args.show_types || args.mime.is_some());
+        finish_output(&mut out, result);
+        note!(quiet, "Time elapsed: {:.2?}", start_time.elapsed());
+    }
+}
+
+/// 刷新输出并报告写入错误
+fn finish_output(out: &mut dyn Write, result: io::Result<()>) {
+    if let Err(err) = result.and_then(|_| out.flush()) {
+        // 管道另一端提前关闭（例如 `| head`）时直接退出
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("Error writing output: {}", err);
+        }
+    }
+}
+
+/// 输出校验结果：不一致、缺失和多余的文件，`summary` 为 true 时之后输出汇总
+fn write_verify(
+    out: &mut dyn Write,
+    report: &hash::VerifyReport,
+    missing: &[&String],
+    skipped: usize,
+    summary: bool,
+) -> io::Result<()> {
+    for file in &report.mismatched {
+        writeln!(out, "{} {}", "MISMATCH".red().bold(), file)?;
+    }
+    for file in missing {
+        writeln!(out, "{} {}", "MISSING ".red().bold(), file)?;
+    }
+    for file in &report.extra {
+        writeln!(out, "{} {}", "EXTRA   ".yellow().bold(), file)?;
+    }
+    if !summary {
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", "Verify summary:".yellow().bold())?;
+    writeln!(out, "OK        : {}", report.ok.to_string().green().bold())?;
+    writeln!(out, "Mismatched: {}", report.mismatched.len().to_string().red().bold())?;
+    writeln!(out, "Missing   : {}", missing.len().to_string().red().bold())?;
+    writeln!(out, "Extra     : {}", report.extra.len().to_string().yellow().bold())?;
+    if skipped > 0 {
+        writeln!(out, "Skipped   : {} (present but excluded by filters)", skipped)?;
+    }
+    Ok(())
+}
+
+/// 输出差异汇总
+fn write_diff_summary(out: &mut dyn Write, diff_stats: &diff::DiffStats) -> io::Result<()> {
+    writeln!(out, "\n{}", "Diff summary:".yellow().bold())?;
+    writeln!(
+        out,
+        "Added files  : {} ({})",
+        diff_stats.added_files.to_string().green().bold(),
+        format_size(diff_stats.added_bytes).green()
+    )?;
+    writeln!(
+        out,
+        "Removed files: {} ({})",
+        diff_stats.removed_files.to_string().red().bold(),
+        format_size(diff_stats.removed_bytes).red()
+    )?;
+    writeln!(
+        out,
+        "Resized files: {}",
+        diff_stats.resized_files.to_string().yellow().bold()
+    )?;
+    writeln!(
+        out,
+        "Total size   : {} → {} ({})",
+        format_size(diff_stats.old_bytes),
+        format_size(diff_stats.new_bytes),
+        format_size_delta(diff_stats.old_bytes, diff_stats.new_bytes).bold()
+    )
+}
+
+/// 输出树结构，`summary` 为 true 时之后输出汇总统计
+fn write_output(
+    out: &mut dyn Write,
+    tree: Option<&tree::TreeNode>,
+    display: &print::DisplayOptions,
+    summary: bool,
+    show_types: bool,
+) -> io::Result<()> {
+    if let Some(tree) = tree {
+        list_files(tree, display, out)?;
+    }
+    if summary {
+        write_summary(out, tree, show_types)?;
+    }
+    Ok(())
+}
+
+/// 只输出总计，每行一个 `key=value`，数值不带单位和分隔符，便于脚本解析
+fn write_totals(out: &mut dyn Write, tree: Option<&tree::TreeNode>) -> io::Result<()> {
+    let stats = tree.map(FileStats::from_tree).unwrap_or(FileStats {
+        total_files: 0,
+        total_dirs: 0,
+        total_bytes: 0,
+    });
+    writeln!(out, "files={}", stats.total_files)?;
+    writeln!(out, "dirs={}", stats.total_dirs)?;
+    writeln!(out, "bytes={}", stats.total_bytes)?;
+    if let Some(line_counts) = tree.and_then(|tree| tree.lines()) {
+        writeln!(out, "lines={}", line_counts.total)?;
+        writeln!(out, "code={}", line_counts.code)?;
+        writeln!(out, "comment={}", line_counts.comment)?;
+        writeln!(out, "blank={}", line_counts.blank)?;
+    }
+    Ok(())
+}
 
-    // Initialize stats counter
-    let stats = Arc::new(Mutex::new(FileStats {
+/// 输出汇总统计；统计信息直接从树中计算，与显示方式无关
+fn write_summary(
+    out: &mut dyn Write,
+    tree: Option<&tree::TreeNode>,
+    show_types: bool,
+) -> io::Result<()> {
+    let stats = tree.map(FileStats::from_tree).unwrap_or(FileStats {
         total_files: 0,
         total_dirs: 0,
         total_bytes: 0,
-    }));
-
-    let path = Path::new(&directory);
-    list_files(
-        path,
-        "",
-        &[],
-        path,
-        &ext,
-        &ignore_dirs,
-        Arc::clone(&stats),
-        min_size,
-        max_size,
-        args.include_children,
-        args.show_stats_only,
-        args.max_depth,
-        pattern.as_ref(),  // 传递正则表达式引用
-    );
-
-    // Print summary statistics
-    let elapsed = start_time.elapsed();
-    let stats = stats.lock().unwrap();
-    // 计算需要减去的目录数量
-    let mut num_exclude_dirs = 0;
-    for path in &args.paths {
-        let path = Path::new(path);
-        if path.is_dir() {
-            num_exclude_dirs += 1;
-        }
-    }
-    println!("\n{}", "Summary:".yellow().bold());
-    println!(
+    });
+    writeln!(out, "\n{}", "Summary:".yellow().bold())?;
+    writeln!(
+        out,
         "Total files : {}",
         stats.total_files.to_string().blue().bold()
-    );
-    println!(
+    )?;
+    writeln!(
+        out,
         "Total dirs  : {}",
-        (stats.total_dirs.saturating_sub(num_exclude_dirs)).to_string().blue().bold()
-    );
-    println!(
+        stats.total_dirs.to_string().blue().bold()
+    )?;
+    writeln!(
+        out,
         "Total size  : {}",
         format_size(stats.total_bytes).green().bold()
-    );
-    println!("Time elapsed: {:.2?}", elapsed);
+    )?;
+    if let Some(line_counts) = tree.and_then(|tree| tree.lines()) {
+        writeln!(
+            out,
+            "Total lines : {} (code {}, comment {}, blank {})",
+            file_size::format_count(line_counts.total).blue().bold(),
+            file_size::format_count(line_counts.code),
+            file_size::format_count(line_counts.comment),
+            file_size::format_count(line_counts.blank)
+        )?;
+
+        writeln!(out, "\n{}", "Lines by language:".yellow().bold())?;
+        writeln!(
+            out,
+            "{:<14} {:>8} {:>10} {:>10} {:>10} {:>10}",
+            "Language", "Files", "Code", "Comment", "Blank", "Total"
+        )?;
+        for (language, files, counts) in lines::by_language(tree.unwrap()) {
+            writeln!(
+                out,
+                "{:<14} {:>8} {:>10} {:>10} {:>10} {:>10}",
+                language,
+                file_size::format_count(files),
+                file_size::format_count(counts.code),
+                file_size::format_count(counts.comment),
+                file_size::format_count(counts.blank),
+                file_size::format_count(counts.total)
+            )?;
+        }
+    }
+    if let Some(tree) = tree.filter(|_| show_types) {
+        writeln!(out, "\n{}", "Files by type:".yellow().bold())?;
+        writeln!(out, "{:<32} {:>8} {:>12}", "Type", "Files", "Size")?;
+        for (mime, files, size) in magic::by_type(tree) {
+            writeln!(
+                out,
+                "{:<32} {:>8} {:>12}",
+                mime,
+                file_size::format_count(files),
+                format_size(size)
+            )?;
+        }
+    }
+    Ok(())
 }
 
 /// 处理输入路径，将其分割为目录和模式
@@ -232,7 +980,7 @@ fn process_paths(paths: &[String]) -> (PathBuf, String) {
     }
     
     // 如果只有一个参数且它是模式，使用当前目录
-    if directory == PathBuf::from(".") && !pattern.is_empty() && paths.len() == 1 {
+    if directory == Path::new(".") && !pattern.is_empty() && paths.len() == 1 {
         // 保持当前目录
     } else if pattern.is_empty() && paths.len() == 1 {
         // 如果只有一个参数且不含通配符，视为目录
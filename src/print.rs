@@ -1,11 +1,18 @@
 use colored::Colorize;
 
-use crate::file_size::format_size;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::file_size::{format_count, format_size};
+use crate::git;
+use crate::icons;
+use crate::lines::LineCounts;
+use crate::ls_colors::{ls_colors, paint};
+use crate::snapshot::format_timestamp;
 use crate::tree::TreeNode;
-use crate::FileStats;
 
 /// 生成树形结构的前缀
-fn generate_tree_prefix(is_last_items: &[bool]) -> String {
+pub fn generate_tree_prefix(is_last_items: &[bool]) -> String {
     let mut result = String::new();
 
     // Handle parent levels
@@ -30,138 +37,589 @@ fn generate_tree_prefix(is_last_items: &[bool]) -> String {
     result
 }
 
-/// 打印树结构 - 显示目录统计信息
-pub fn print_tree_num(
+/// 占比条的字符宽度
+const BAR_WIDTH: usize = 10;
+
+/// 生成占比条，例如 `[██████▌   ]  65.3% (12.1% of root)`
+fn generate_share_bar(size: u64, parent_size: u64, root_size: u64) -> String {
+    let ratio = |total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            size as f64 / total as f64
+        }
+    };
+    let parent_ratio = ratio(parent_size);
+    let root_ratio = ratio(root_size);
+
+    // 用 1/8 宽度的方块字符表示小数部分
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (parent_ratio * (BAR_WIDTH * 8) as f64).round() as usize;
+    let full = eighths / 8;
+    let mut bar: String = "█".repeat(full);
+    if full < BAR_WIDTH {
+        bar.push(PARTIAL[eighths % 8]);
+        bar.push_str(&" ".repeat(BAR_WIDTH - full - 1));
+    }
+
+    format!(
+        "[{}] {:>5.1}% ({:.1}% of root)",
+        bar.magenta(),
+        parent_ratio * 100.0,
+        root_ratio * 100.0
+    )
+}
+
+/// 行数统计的显示文本，例如 `, 1,234 lines`；未统计行数时为空
+fn format_lines(lines: Option<LineCounts>) -> String {
+    match lines {
+        Some(lines) => format!(", {}", format!("{} lines", format_count(lines.total)).bright_cyan()),
+        None => String::new(),
+    }
+}
+
+/// 压缩包的显示文本，例如 `, 12.00 MB compressed`；普通目录为空
+fn format_archive(node: &TreeNode) -> String {
+    match node.archive_size() {
+        Some(size) => format!(", {}", format!("{} compressed", format_size(size)).magenta()),
+        None => String::new(),
+    }
+}
+
+/// 子树中各 git 状态的文件数，例如 ` [2 untracked, 1 modified]`；没有标注时为空
+fn format_git_counts(node: &TreeNode) -> String {
+    let counts = git::count_states(node);
+    if counts.is_empty() {
+        return String::new();
+    }
+    let parts: Vec<String> = counts
+        .iter()
+        .map(|(state, count)| state.colorize(&format!("{} {}", format_count(*count), state.label())).to_string())
+        .collect();
+    format!(" [{}]", parts.join(", "))
+}
+
+/// 目录的提交历史，例如 ` · 12 commits by 3 authors, last 2024-03-01`；未统计时为空
+fn format_history(node: &TreeNode) -> String {
+    let TreeNode::Directory { history: Some(history), .. } = node else {
+        return String::new();
+    };
+    // 只显示日期部分
+    let date: String = format_timestamp(history.last_change).chars().take(10).collect();
+    let plural = |count: usize, word: &str| {
+        format!("{} {}{}", format_count(count), word, if count == 1 { "" } else { "s" })
+    };
+    format!(
+        " · {} by {}, last {}",
+        plural(history.commits, "commit").magenta(),
+        plural(history.authors, "author").magenta(),
+        date.bright_magenta()
+    )
+}
+
+/// 目录名按 LS_COLORS 着色，展开的压缩包按文件名后缀着色；启用图标时加上图标
+fn paint_dir(node: &TreeNode, options: &DisplayOptions) -> String {
+    let colors = ls_colors();
+    let (style, icon) = match node.archive_size() {
+        Some(_) => (colors.file_style(node.path()), icons::file_icon(node.name())),
+        None => (colors.dir_style(node.path()), icons::dir_icon(node.name())),
+    };
+    with_icon(paint(node.name(), style), icon, style, options)
+}
+
+/// 文件名按 LS_COLORS 着色；启用图标时加上图标
+fn paint_file(name: &str, path: &Path, options: &DisplayOptions) -> String {
+    let style = ls_colors().file_style(path);
+    with_icon(paint(name, style), icons::file_icon(name), style, options)
+}
+
+/// 在名称前加上与名称同色的图标
+fn with_icon(name: String, icon: char, style: Option<&str>, options: &DisplayOptions) -> String {
+    if options.icons {
+        format!("{} {}", paint(&icon.to_string(), style), name)
+    } else {
+        name
+    }
+}
+
+/// 多个节点的行数之和，都未统计行数时为 None
+fn sum_lines<'a>(nodes: impl IntoIterator<Item = &'a TreeNode>) -> Option<LineCounts> {
+    let mut total: Option<LineCounts> = None;
+    for lines in nodes.into_iter().filter_map(|n| n.lines()) {
+        *total.get_or_insert_with(LineCounts::default) += lines;
+    }
+    total
+}
+
+/// 输出树结构时的显示选项
+#[derive(Clone, Default)]
+pub struct DisplayOptions {
+    /// 目录统计包含子目录中的文件
+    pub include_children: bool,
+    /// 只显示目录统计，不显示文件
+    pub show_stats_only: bool,
+    /// 显示占父目录和根目录的比例条
+    pub show_bars: bool,
+    /// 每个目录最多显示的文件数，0 表示不限制
+    pub max_files_per_dir: usize,
+    /// 小于该大小的目录折叠为一行，0 表示不启用
+    pub collapse_size: u64,
+    /// 文件数少于该值的目录折叠为一行，0 表示不启用
+    pub collapse_files: usize,
+    /// -n 模式下占父目录比例低于该百分比的子目录合并为 <other>
+    pub min_share: f64,
+    /// 在名称前显示 Nerd Font 文件类型图标
+    pub icons: bool,
+}
+
+/// 判断目录是否低于折叠阈值（根目录不折叠，阈值为0表示不启用）
+fn is_collapsed(node: &TreeNode, is_root: bool, options: &DisplayOptions) -> bool {
+    match node {
+        TreeNode::Directory { total_files, total_size, .. } if !is_root => {
+            (options.collapse_size > 0 && *total_size < options.collapse_size)
+                || (options.collapse_files > 0 && *total_files < options.collapse_files)
+        }
+        _ => false,
+    }
+}
+
+/// 将树结构写入任意输出的格式，例如终端、文件或测试用的缓冲区
+pub trait Formatter {
+    fn write_tree(&self, tree: &TreeNode, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// 显示目录和文件完整结构（默认格式）
+pub struct TreeFormatter {
+    pub options: DisplayOptions,
+}
+
+impl Formatter for TreeFormatter {
+    fn write_tree(&self, tree: &TreeNode, out: &mut dyn Write) -> io::Result<()> {
+        // 根目录的总大小作为占比的基准
+        let root_size = tree.total_size();
+        write_tree_file(tree, &[], out, &self.options, root_size, root_size)
+    }
+}
+
+/// 只显示目录统计信息（-n）
+pub struct StatsFormatter {
+    pub options: DisplayOptions,
+}
+
+impl Formatter for StatsFormatter {
+    fn write_tree(&self, tree: &TreeNode, out: &mut dyn Write) -> io::Result<()> {
+        let root_size = tree.total_size();
+        write_tree_num(tree, &[], out, &self.options, root_size, root_size)
+    }
+}
+
+/// 根据显示选项选择输出格式
+pub fn formatter_for(options: &DisplayOptions) -> Box<dyn Formatter> {
+    if options.show_stats_only {
+        Box::new(StatsFormatter { options: options.clone() })
+    } else {
+        Box::new(TreeFormatter { options: options.clone() })
+    }
+}
+
+/// 输出目录统计信息
+fn write_tree_num(
     node: &TreeNode,
-    prefix: &str,
-    is_last_items: &[bool], 
-    stats: &mut FileStats,
-    include_children: bool,
-) {
+    is_last_items: &[bool],
+    out: &mut dyn Write,
+    options: &DisplayOptions,
+    parent_size: u64,
+    root_size: u64,
+) -> io::Result<()> {
     match node {
-        TreeNode::Directory { name, files, dirs, total_files, total_size, direct_files, direct_size, .. } => {
+        TreeNode::Directory { files, dirs, total_files, total_size, direct_files, direct_size, .. } => {
+            let collapsed = is_collapsed(node, is_last_items.is_empty(), options);
+
             // 使用新的字段，根据include_children选择显示方式
-            let (_total_files, _total_size) = if include_children {
-                (*total_files, *total_size)
+            let (_total_files, _total_size, _lines) = if options.include_children {
+                (*total_files, *total_size, node.lines())
             } else {
-                (*direct_files, *direct_size)
+                (*direct_files, *direct_size, sum_lines(files))
             };
 
             // Display directory with file count and size
             if !is_last_items.is_empty() {
                 let tree_prefix = generate_tree_prefix(is_last_items);
-                print!("{}{}{} ", prefix, tree_prefix, name.blue().bold());
-                
+                write!(out, "{}{} ", tree_prefix, paint_dir(node, options))?;
+
                 if _total_files > 0 {
-                    print!(
-                        "({} dirs, {}, {})",
+                    write!(
+                        out,
+                        "({} dirs, {}, {}{}{})",
                         dirs.len().to_string().cyan(),
                         format!("{} files", _total_files).green(),
-                        format_size(_total_size).yellow()
-                    );
+                        format_size(_total_size).yellow(),
+                        format_archive(node),
+                        format_lines(_lines)
+                    )?;
+                }
+                write!(out, "{}{}", format_git_counts(node), format_history(node))?;
+                if options.show_bars {
+                    write!(out, " {}", generate_share_bar(node.disk_size(), parent_size, root_size))?;
+                }
+                if collapsed && !dirs.is_empty() {
+                    write!(out, " {}", format!("[+{} dirs collapsed]", format_count(node.count_dirs())).dimmed())?;
                 }
             } else {
                 // Root directory special handling
-                print!("Directory: {} ", name.blue().bold());
-                
+                write!(out, "Directory: {} ", paint_dir(node, options))?;
+
                 if _total_files > 0 {
-                    print!(
-                        "({} dirs, {}, {})",
+                    write!(
+                        out,
+                        "({} dirs, {}, {}{})",
                         dirs.len().to_string().cyan(),
                         format!("{} files", _total_files).green(),
-                        format_size(_total_size).yellow()
-                    );
+                        format_size(_total_size).yellow(),
+                        format_lines(_lines)
+                    )?;
                 }
+                write!(out, "{}{}", format_git_counts(node), format_history(node))?;
             }
-            println!();
-            
-            // Update statistics
-            stats.total_dirs += 1;
-            stats.total_files += files.len(); // 直接使用files数组的长度
-            stats.total_bytes += *total_size;
-            
+            writeln!(out)?;
+
+            // 折叠的目录不再展开子目录
+            if collapsed {
+                return Ok(());
+            }
+
+            // 占比低于阈值的子目录合并为一个 <other> 节点
+            let (kept_dirs, other_dirs): (Vec<&TreeNode>, Vec<&TreeNode>) = dirs.iter().partition(|child| {
+                let child_size = match child {
+                    TreeNode::Directory { total_size, .. } => *total_size,
+                    TreeNode::File { size, .. } => *size,
+                };
+                options.min_share <= 0.0 || child_size as f64 >= *total_size as f64 * options.min_share / 100.0
+            });
+            let total_items = kept_dirs.len() + usize::from(!other_dirs.is_empty());
+
             // 处理子目录
-            for (idx, child) in dirs.iter().enumerate() {
-                let is_last = idx == dirs.len() - 1;
+            for (idx, child) in kept_dirs.iter().enumerate() {
+                let is_last = idx == total_items - 1;
                 let mut new_is_last_items = is_last_items.to_vec();
                 new_is_last_items.push(is_last);
-                
-                print_tree_num(child, prefix, &new_is_last_items, stats, include_children);
+
+                write_tree_num(child, &new_is_last_items, out, options, *total_size, root_size)?;
+            }
+
+            if !other_dirs.is_empty() {
+                let mut new_is_last_items = is_last_items.to_vec();
+                new_is_last_items.push(true);
+                write_other_dirs(&other_dirs, &new_is_last_items, out, options, *total_size, root_size)?;
             }
         },
         TreeNode::File { .. } => {
-            // 文件节点不单独打印
+            // 文件节点不单独输出
         }
     }
+    Ok(())
+}
+
+/// 输出合并后的 <other> 节点，与普通目录一样根据 include_children 统计整个子树或直接文件
+fn write_other_dirs(
+    other_dirs: &[&TreeNode],
+    is_last_items: &[bool],
+    out: &mut dyn Write,
+    options: &DisplayOptions,
+    parent_size: u64,
+    root_size: u64,
+) -> io::Result<()> {
+    let mut other_files = 0;
+    let mut other_size = 0;
+    let mut other_disk_size = 0;
+    let mut other_nested_dirs = 0;
+    let mut other_lines: Option<LineCounts> = None;
+    for dir in other_dirs {
+        if let TreeNode::Directory { files, total_files, total_size, direct_files, direct_size, .. } = dir {
+            let (dir_files, dir_size, dir_lines) = if options.include_children {
+                (*total_files, *total_size, dir.lines())
+            } else {
+                (*direct_files, *direct_size, sum_lines(files))
+            };
+            other_files += dir_files;
+            other_size += dir_size;
+            other_disk_size += dir.disk_size();
+            other_nested_dirs += dir.count_dirs();
+            if let Some(lines) = dir_lines {
+                *other_lines.get_or_insert_with(LineCounts::default) += lines;
+            }
+        }
+    }
+
+    let tree_prefix = generate_tree_prefix(is_last_items);
+    let label = format!(
+        "<other: {} {}>",
+        format_count(other_dirs.len()),
+        if other_dirs.len() == 1 { "dir" } else { "dirs" }
+    );
+    write!(out, "{}{} ", tree_prefix, label.dimmed())?;
+    if other_files > 0 {
+        write!(
+            out,
+            "({} dirs, {}, {}{})",
+            other_nested_dirs.to_string().cyan(),
+            format!("{} files", other_files).green(),
+            format_size(other_size).yellow(),
+            format_lines(other_lines)
+        )?;
+    }
+    if options.show_bars {
+        write!(out, " {}", generate_share_bar(other_disk_size, parent_size, root_size))?;
+    }
+    writeln!(out)
 }
 
-/// 打印树结构 - 显示目录和文件完整结构
-pub fn print_tree_file(
+/// 输出目录和文件完整结构
+fn write_tree_file(
     node: &TreeNode,
-    prefix: &str,
-    is_last_items: &[bool], 
-    stats: &mut FileStats,
-) {
+    is_last_items: &[bool],
+    out: &mut dyn Write,
+    options: &DisplayOptions,
+    parent_size: u64,
+    root_size: u64,
+) -> io::Result<()> {
     match node {
-        TreeNode::Directory { name, files, dirs, total_size, .. } => {
+        TreeNode::Directory { files, dirs, total_files, total_size, direct_size, .. } => {
+            let collapsed = is_collapsed(node, is_last_items.is_empty(), options);
+
             // 显示目录名
-            if !is_last_items.is_empty() {  // 移除多余的括号
+            if collapsed {
+                // 折叠的目录只显示一行汇总
+                let tree_prefix = generate_tree_prefix(is_last_items);
+                write!(
+                    out,
+                    "{}{} {}",
+                    tree_prefix,
+                    paint_dir(node, options),
+                    format!(
+                        "(collapsed: {} files, {} dirs, {}{}{})",
+                        format_count(*total_files),
+                        format_count(node.count_dirs()),
+                        format_size(*total_size),
+                        node.archive_size()
+                            .map(|size| format!(", {} compressed", format_size(size)))
+                            .unwrap_or_default(),
+                        node.lines()
+                            .map(|lines| format!(", {} lines", format_count(lines.total)))
+                            .unwrap_or_default()
+                    )
+                    .dimmed()
+                )?;
+                write!(out, "{}", format_git_counts(node))?;
+                if options.show_bars {
+                    write!(out, " {}", generate_share_bar(node.disk_size(), parent_size, root_size))?;
+                }
+                return writeln!(out);
+            } else if !is_last_items.is_empty() {  // 移除多余的括号
                 let tree_prefix = generate_tree_prefix(is_last_items);
-                println!("{}{}{} ({} dirs, {})", prefix, tree_prefix, name.blue().bold(), dirs.len().to_string().cyan(), format_size(*total_size).yellow());
+                write!(out, "{}{} ({} dirs, {}{}{}){}", tree_prefix, paint_dir(node, options), dirs.len().to_string().cyan(), format_size(*total_size).yellow(), format_archive(node), format_lines(node.lines()), format_git_counts(node))?;
+                if options.show_bars {
+                    write!(out, " {}", generate_share_bar(node.disk_size(), parent_size, root_size))?;
+                }
+                writeln!(out)?;
             } else {
                 // 根目录特殊处理
-                println!("Directory: {} ({} dirs, {})", name.blue().bold(), dirs.len().to_string().cyan(), format_size(*total_size).yellow());
+                writeln!(out, "Directory: {} ({} dirs, {}{}){}", paint_dir(node, options), dirs.len().to_string().cyan(), format_size(*total_size).yellow(), format_lines(node.lines()), format_git_counts(node))?;
             }
-            
-            // 更新统计信息
-            stats.total_dirs += 1;
-            stats.total_bytes += *total_size;
-            
+
+            // 文件过多时只显示最大的N个文件，保持原有的名称顺序
+            let shown_files: Vec<&TreeNode> = if options.max_files_per_dir > 0 && files.len() > options.max_files_per_dir {
+                let mut indices: Vec<usize> = (0..files.len()).collect();
+                indices.sort_by_key(|&i| match &files[i] {
+                    TreeNode::File { size, .. } => std::cmp::Reverse(*size),
+                    TreeNode::Directory { .. } => std::cmp::Reverse(0),
+                });
+                indices.truncate(options.max_files_per_dir);
+                indices.sort_unstable();
+                indices.into_iter().map(|i| &files[i]).collect()
+            } else {
+                files.iter().collect()
+            };
+            let hidden_files = files.len() - shown_files.len();
+
             // 先处理文件
-            let total_items = files.len() + dirs.len();
+            let total_items = shown_files.len() + usize::from(hidden_files > 0) + dirs.len();
             let mut current_idx = 0;
-            
-            for file in files {
+
+            for file in &shown_files {
                 let is_last = current_idx == total_items - 1;
                 let mut new_is_last_items = is_last_items.to_vec();
                 new_is_last_items.push(is_last);
-                
-                print_tree_file(file, prefix, &new_is_last_items, stats);
+
+                write_tree_file(file, &new_is_last_items, out, options, *total_size, root_size)?;
                 current_idx += 1;
             }
-            
+
+            // 被省略的文件用一行汇总
+            if hidden_files > 0 {
+                // 目录中文件的总大小减去已显示的文件
+                let shown_size: u64 = shown_files.iter().map(|f| f.total_size()).sum();
+                let hidden_size = direct_size.saturating_sub(shown_size);
+
+                let is_last = current_idx == total_items - 1;
+                let mut new_is_last_items = is_last_items.to_vec();
+                new_is_last_items.push(is_last);
+                let tree_prefix = generate_tree_prefix(&new_is_last_items);
+                writeln!(
+                    out,
+                    "{}{}",
+                    tree_prefix,
+                    format!("… and {} more files ({})", format_count(hidden_files), format_size(hidden_size)).dimmed()
+                )?;
+                current_idx += 1;
+            }
+
             // 再处理目录
             for dir in dirs {
                 let is_last = current_idx == total_items - 1;
                 let mut new_is_last_items = is_last_items.to_vec();
                 new_is_last_items.push(is_last);
-                
-                print_tree_file(dir, prefix, &new_is_last_items, stats);
+
+                write_tree_file(dir, &new_is_last_items, out, options, *total_size, root_size)?;
                 current_idx += 1;
             }
         },
-        TreeNode::File { name, path, size } => {
+        TreeNode::File { name, path, size, hash, lines, git: git_code, .. } => {
             // 显示文件名和大小
             let tree_prefix = generate_tree_prefix(is_last_items);
-            
-            // 检查是否是可执行文件（.exe扩展名）
-            let is_exe = path.extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| ext.eq_ignore_ascii_case("exe"))
-                .unwrap_or(false);
-            
-            // 根据文件类型选择颜色
-            let colored_name = if is_exe {
-                name.green()
-            } else {
-                name.normal()
+
+            // 按 LS_COLORS 根据文件类型、权限和扩展名着色
+            let colored_name = paint_file(name, path, options);
+
+            // git 状态显示在最后，例如 ` [staged, modified]`
+            let git_label = match git_code {
+                Some(code) => {
+                    let labels: Vec<String> = git::states(code)
+                        .map(|state| state.colorize(state.label()).to_string())
+                        .collect();
+                    format!(" [{}]", labels.join(", "))
+                }
+                None => String::new(),
             };
-            
-            println!("{}{}{} ({})", prefix, tree_prefix, colored_name, format_size(*size).yellow());
-            
-            // 更新统计信息
-            stats.total_files += 1;
-            stats.total_bytes += *size;
+
+            match hash {
+                // 哈希只显示前16位
+                Some(hash) => writeln!(
+                    out,
+                    "{}{} ({}{}, {}){}",
+                    tree_prefix,
+                    colored_name,
+                    format_size(*size).yellow(),
+                    format_lines(*lines),
+                    hash.chars().take(16).collect::<String>().dimmed(),
+                    git_label
+                )?,
+                None => writeln!(out, "{}{} ({}{}){}", tree_prefix, colored_name, format_size(*size).yellow(), format_lines(*lines), git_label)?,
+            }
         }
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn tree_formatter_writes_into_buffer() {
+        colored::control::set_override(false);
+        let mut root = TreeNode::new_directory("root".into());
+        root.insert_into(Path::new("root"), TreeNode::new_directory("root/src".into()));
+        root.insert_into(Path::new("root/src"), TreeNode::new_file("root/src/main.rs".into(), 2048));
+        root.insert_into(Path::new("root"), TreeNode::new_file("root/README.md".into(), 100));
+
+        let formatter = TreeFormatter { options: DisplayOptions::default() };
+        let mut out = Vec::new();
+        formatter.write_tree(&root, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Directory: root (1 dirs, 2.10 KB)\n\
+             ├── README.md (100 bytes)\n\
+             └── src (0 dirs, 2.00 KB)\n    \
+             └── main.rs (2.00 KB)\n"
+        );
+    }
+
+    /// root/a (100)、root/big/x (600)、root/big/deep/y (200)、root/small/z (100)
+    fn stats_sample() -> TreeNode {
+        let mut root = TreeNode::new_directory("root".into());
+        root.insert_into(Path::new("root"), TreeNode::new_file("root/a".into(), 100));
+        for dir in ["root/big", "root/big/deep", "root/small"] {
+            let parent = Path::new(dir).parent().unwrap();
+            root.insert_into(parent, TreeNode::new_directory(dir.into()));
+        }
+        root.insert_into(Path::new("root/big"), TreeNode::new_file("root/big/x".into(), 600));
+        root.insert_into(Path::new("root/big/deep"), TreeNode::new_file("root/big/deep/y".into(), 200));
+        root.insert_into(Path::new("root/small"), TreeNode::new_file("root/small/z".into(), 100));
+        root
+    }
+
+    fn write_stats(tree: &TreeNode, options: DisplayOptions) -> String {
+        colored::control::set_override(false);
+        let formatter = StatsFormatter { options: DisplayOptions { show_stats_only: true, ..options } };
+        let mut out = Vec::new();
+        formatter.write_tree(tree, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn stats_formatter_shows_share_bars() {
+        let output = write_stats(&stats_sample(), DisplayOptions { show_bars: true, ..Default::default() });
+        assert_eq!(
+            output,
+            "Directory: root (2 dirs, 1 files, 100 bytes)\n\
+             ├── big (1 dirs, 1 files, 600 bytes) [████████  ]  80.0% (80.0% of root)\n\
+             │   └── deep (0 dirs, 1 files, 200 bytes) [██▌       ]  25.0% (20.0% of root)\n\
+             └── small (0 dirs, 1 files, 100 bytes) [█         ]  10.0% (10.0% of root)\n"
+        );
+    }
+
+    #[test]
+    fn stats_formatter_collapses_small_directories() {
+        // big 的整个子树为 800 字节、2 个文件，低于阈值时不再展开 deep
+        let expected = "Directory: root (2 dirs, 1 files, 100 bytes)\n\
+                        ├── big (1 dirs, 1 files, 600 bytes) [+1 dirs collapsed]\n\
+                        └── small (0 dirs, 1 files, 100 bytes)\n";
+        let by_size = write_stats(&stats_sample(), DisplayOptions { collapse_size: 900, ..Default::default() });
+        assert_eq!(by_size, expected);
+        let by_files = write_stats(&stats_sample(), DisplayOptions { collapse_files: 3, ..Default::default() });
+        assert_eq!(by_files, expected);
+
+        // 根目录不折叠
+        let root_only = write_stats(&stats_sample(), DisplayOptions { collapse_size: u64::MAX, ..Default::default() });
+        assert!(root_only.starts_with("Directory: root"));
+    }
+
+    #[test]
+    fn stats_formatter_folds_small_shares_into_other() {
+        let output = write_stats(&stats_sample(), DisplayOptions { min_share: 20.0, ..Default::default() });
+        assert_eq!(
+            output,
+            "Directory: root (2 dirs, 1 files, 100 bytes)\n\
+             ├── big (1 dirs, 1 files, 600 bytes)\n\
+             │   └── deep (0 dirs, 1 files, 200 bytes)\n\
+             └── <other: 1 dir> (0 dirs, 1 files, 100 bytes)\n"
+        );
+
+        // 与其他目录一样，默认只统计被合并目录的直接文件
+        let output = write_stats(&stats_sample(), DisplayOptions { min_share: 90.0, ..Default::default() });
+        assert_eq!(
+            output,
+            "Directory: root (2 dirs, 1 files, 100 bytes)\n\
+             └── <other: 2 dirs> (1 dirs, 2 files, 700 bytes)\n"
+        );
+
+        let options = DisplayOptions { min_share: 90.0, include_children: true, ..Default::default() };
+        assert_eq!(
+            write_stats(&stats_sample(), options),
+            "Directory: root (2 dirs, 4 files, 1000 bytes)\n\
+             └── <other: 2 dirs> (1 dirs, 3 files, 900 bytes)\n"
+        );
+    }
 }
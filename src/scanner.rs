@@ -0,0 +1,219 @@
+use regex::Regex;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::contains::ContentFilter;
+use crate::filter::{self, All, And, Filter};
+use crate::list_files::build_directory_tree;
+use crate::tree::TreeNode;
+
+/// 将 `*.rs` 这样的通配模式转换为正则表达式
+pub fn glob_to_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let regex_pattern = pattern
+        .replace(".", "\\.")  // Escape dots
+        .replace("*", ".*")   // Convert * to .*
+        .replace("?", ".");   // Convert ? to .
+    Regex::new(&regex_pattern)
+}
+
+/// 无法读取的目录或压缩包的回调
+pub type ErrorHandler = Box<dyn Fn(&Path, &io::Error) + Send + Sync>;
+
+/// 扫描选项：跳过的目录、深度限制和文件的过滤条件
+pub struct ScanOptions {
+    /// 跳过这些名称的目录
+    pub ignore_dirs: Vec<String>,
+    /// 最大深度，0 表示不限制
+    pub max_depth: usize,
+    /// 文件需满足的过滤条件
+    pub filter: Box<dyn Filter>,
+    /// 将压缩包展开为虚拟目录，包内文件同样应用过滤条件
+    pub archives: bool,
+    /// 包含以点开头的隐藏文件和目录
+    pub all: bool,
+    /// 无法读取子目录或压缩包时调用，未设置时跳过该条目
+    pub on_error: Option<ErrorHandler>,
+}
+
+impl ScanOptions {
+    /// 是否跳过该名称的条目：以点开头的隐藏条目（未启用 `all` 时）和被忽略的目录
+    pub fn skips(&self, name: &str) -> bool {
+        (!self.all && name.starts_with('.')) || self.ignore_dirs.iter().any(|ignored| ignored == name)
+    }
+
+    /// 当前深度的目录是否超出深度限制
+    pub fn beyond_depth(&self, depth: usize) -> bool {
+        self.max_depth > 0 && depth > self.max_depth
+    }
+
+    /// 报告无法读取的目录或压缩包
+    pub fn report_error(&self, dir: &Path, err: &io::Error) {
+        if let Some(on_error) = &self.on_error {
+            on_error(dir, err);
+        }
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            ignore_dirs: Vec::new(),
+            max_depth: 0,
+            filter: Box::new(All),
+            archives: false,
+            all: false,
+            on_error: None,
+        }
+    }
+}
+
+/// 通过构建器方法设置的过滤参数，保存快照时记录下来；`filter` 添加的自定义条件不在其中
+pub struct FilterParams {
+    /// 扩展名（不含点），空字符串表示不限制
+    pub ext: String,
+    pub min_size: u64,
+    pub max_size: u64,
+    /// 路径需匹配的正则表达式
+    pub pattern: Option<String>,
+    /// 文件内容需匹配的正则表达式
+    pub contains: Option<String>,
+}
+
+impl Default for FilterParams {
+    fn default() -> Self {
+        FilterParams {
+            ext: String::new(),
+            min_size: 0,
+            max_size: u64::MAX,
+            pattern: None,
+            contains: None,
+        }
+    }
+}
+
+/// 并行扫描目录树的构建器
+///
+/// `Scanner::new(root).ext("rs").max_depth(3).scan()` 返回扫描得到的 `TreeNode`，
+/// 没有匹配的文件时返回空的根目录节点；根目录无法读取时返回错误，
+/// 子目录的错误通过 `on_error` 交给调用者处理。
+pub struct Scanner {
+    root: PathBuf,
+    options: ScanOptions,
+    params: FilterParams,
+}
+
+impl Scanner {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Scanner {
+            root: root.as_ref().to_path_buf(),
+            options: ScanOptions::default(),
+            params: FilterParams::default(),
+        }
+    }
+
+    /// 添加过滤条件，与已有条件同时满足；后添加的条件先判断
+    pub fn filter<F: Filter + 'static>(mut self, filter: F) -> Self {
+        let previous = std::mem::replace(&mut self.options.filter, Box::new(All));
+        self.options.filter = Box::new(And(filter, previous));
+        self
+    }
+
+    /// 只统计指定扩展名的文件（不含点）
+    pub fn ext(mut self, ext: &str) -> Self {
+        if ext.is_empty() {
+            return self;
+        }
+        self.params.ext = ext.to_string();
+        self.filter(filter::Ext(ext.to_string()))
+    }
+
+    /// 跳过小于指定字节数的文件
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        if min_size == 0 {
+            return self;
+        }
+        self.params.min_size = self.params.min_size.max(min_size);
+        self.filter(filter::Size { min: min_size, max: u64::MAX })
+    }
+
+    /// 跳过大于指定字节数的文件
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        if max_size == u64::MAX {
+            return self;
+        }
+        self.params.max_size = self.params.max_size.min(max_size);
+        self.filter(filter::Size { min: 0, max: max_size })
+    }
+
+    /// 只统计路径匹配正则表达式的文件，通配模式可用 `glob_to_regex` 转换
+    pub fn pattern(mut self, pattern: Regex) -> Self {
+        self.params.pattern = Some(pattern.as_str().to_string());
+        self.filter(filter::Pattern(pattern))
+    }
+
+    /// 只统计内容匹配的文本文件；读取内容的开销最大，总是在其他条件之后判断
+    pub fn contains(mut self, filter: ContentFilter) -> Self {
+        self.params.contains = Some(filter.regex.as_str().to_string());
+        let previous = std::mem::replace(&mut self.options.filter, Box::new(All));
+        self.options.filter = Box::new(And(previous, filter));
+        self
+    }
+
+    /// 跳过指定名称的目录，可多次调用
+    pub fn ignore(mut self, dir_name: &str) -> Self {
+        self.options.ignore_dirs.push(dir_name.to_string());
+        self
+    }
+
+    /// 限制扫描深度，0 表示不限制
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = max_depth;
+        self
+    }
+
+    /// 将压缩包展开为虚拟目录
+    pub fn archives(mut self, archives: bool) -> Self {
+        self.options.archives = archives;
+        self
+    }
+
+    /// 包含以点开头的隐藏文件和目录
+    pub fn all(mut self, all: bool) -> Self {
+        self.options.all = all;
+        self
+    }
+
+    /// 设置无法读取子目录或压缩包时的回调，例如输出警告或收集错误
+    pub fn on_error<F>(mut self, on_error: F) -> Self
+    where
+        F: Fn(&Path, &io::Error) + Send + Sync + 'static,
+    {
+        self.options.on_error = Some(Box::new(on_error));
+        self
+    }
+
+    /// 扫描的根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 扫描选项，可用于监听模式等需要重新扫描部分目录的场景
+    pub fn options(&self) -> &ScanOptions {
+        &self.options
+    }
+
+    /// 通过构建器方法设置的过滤参数
+    pub fn params(&self) -> &FilterParams {
+        &self.params
+    }
+
+    /// 扫描目录树
+    pub fn scan(&self) -> io::Result<TreeNode> {
+        // 根目录不存在或无法读取时报错，而不是当作空目录
+        fs::read_dir(&self.root)?;
+        Ok(build_directory_tree(&self.root, &self.options, 1)
+            .unwrap_or_else(|| TreeNode::new_directory(self.root.clone())))
+    }
+}
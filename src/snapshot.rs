@@ -0,0 +1,221 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::file_size::format_size;
+use crate::scanner::Scanner;
+use crate::tree::TreeNode;
+
+/// 扫描时的参数和环境信息
+#[derive(Serialize, Deserialize)]
+pub struct ScanMetadata {
+    pub root: String,
+    pub ext: String,
+    pub ignore_dirs: Vec<String>,
+    pub min_size: u64,
+    pub max_size: u64,
+    pub max_depth: usize,
+    pub pattern: Option<String>,
+    /// 按文件内容过滤的正则表达式
+    #[serde(default)]
+    pub contains: Option<String>,
+    /// 是否包含以点开头的隐藏条目（-a）
+    #[serde(default)]
+    pub all: bool,
+    /// 是否将压缩包展开为虚拟目录（--archives）
+    #[serde(default)]
+    pub archives: bool,
+    /// 扫描时间，Unix 时间戳（秒）
+    pub timestamp: u64,
+    pub host: String,
+    pub version: String,
+}
+
+/// 保存到磁盘的完整扫描结果
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub metadata: ScanMetadata,
+    pub tree: TreeNode,
+}
+
+impl ScanMetadata {
+    /// 记录扫描器的根目录、扫描选项和过滤参数
+    pub fn new(scanner: &Scanner) -> Self {
+        let (root, options, params) = (scanner.root(), scanner.options(), scanner.params());
+        // 保存绝对路径，从不同目录以相对路径扫描的快照才能区分
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        ScanMetadata {
+            root: root.to_string_lossy().into_owned(),
+            ext: params.ext.clone(),
+            ignore_dirs: options.ignore_dirs.clone(),
+            min_size: params.min_size,
+            max_size: params.max_size,
+            max_depth: options.max_depth,
+            pattern: params.pattern.clone(),
+            contains: params.contains.clone(),
+            all: options.all,
+            archives: options.archives,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            host: hostname(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// 两次扫描是否针对同一台主机上的同一目录，且过滤条件相同，结果才可以相互比较
+    pub fn same_scan(&self, other: &ScanMetadata) -> bool {
+        self.host == other.host
+            && self.root == other.root
+            && self.ext == other.ext
+            && self.ignore_dirs == other.ignore_dirs
+            && self.min_size == other.min_size
+            && self.max_size == other.max_size
+            && self.max_depth == other.max_depth
+            && self.pattern == other.pattern
+            && self.contains == other.contains
+            && self.all == other.all
+            && self.archives == other.archives
+    }
+
+    /// 扫描时使用的过滤条件，便于加载快照时显示
+    pub fn describe_filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if !self.ext.is_empty() {
+            filters.push(format!("extension: {}", self.ext));
+        }
+        if !self.ignore_dirs.is_empty() {
+            filters.push(format!("ignoring: {}", self.ignore_dirs.join(", ")));
+        }
+        if self.min_size > 0 {
+            filters.push(format!("min size: {}", format_size(self.min_size)));
+        }
+        if self.max_size < u64::MAX {
+            filters.push(format!("max size: {}", format_size(self.max_size)));
+        }
+        if self.max_depth > 0 {
+            filters.push(format!("max depth: {}", self.max_depth));
+        }
+        if let Some(pattern) = &self.pattern {
+            filters.push(format!("pattern: {}", pattern));
+        }
+        if let Some(contains) = &self.contains {
+            filters.push(format!("contains: {}", contains));
+        }
+        if self.all {
+            filters.push("including hidden entries".to_string());
+        }
+        if self.archives {
+            filters.push("expanding archives".to_string());
+        }
+        filters
+    }
+}
+
+/// 获取主机名，优先使用环境变量
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 将 Unix 时间戳格式化为 UTC 时间，例如 `2024-05-01 08:30:00 UTC`
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+
+    // 由天数计算公历日期（Howard Hinnant 的 civil_from_days 算法）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// 将快照保存为 gzip 压缩的 JSON
+pub fn save<P: AsRef<Path>>(path: P, snapshot: &Snapshot) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+    serde_json::to_writer(&mut encoder, snapshot)?;
+    encoder.finish()?.flush()
+}
+
+/// 从磁盘加载快照
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+    let file = File::open(path)?;
+    let decoder = GzDecoder::new(BufReader::new(file));
+    let snapshot = serde_json::from_reader(BufReader::new(decoder))?;
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ntree-snapshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let scanner = Scanner::new(&dir).ext("rs").max_size(1024).ignore("target").all(true).archives(true);
+
+        let mut tree = TreeNode::new_directory(PathBuf::from("root"));
+        tree.insert_into(Path::new("root"), TreeNode::new_file("root/main.rs".into(), 42));
+        tree.insert_into(Path::new("root"), TreeNode::new_special("root/fifo".into()));
+        let snapshot = Snapshot { metadata: ScanMetadata::new(&scanner), tree };
+
+        let path = dir.join("snapshot.json.gz");
+        save(&path, &snapshot).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(loaded.metadata.same_scan(&snapshot.metadata));
+        assert_eq!(loaded.metadata.timestamp, snapshot.metadata.timestamp);
+        assert_eq!(
+            loaded.metadata.describe_filters(),
+            [
+                "extension: rs",
+                "ignoring: target",
+                "max size: 1.00 KB",
+                "including hidden entries",
+                "expanding archives"
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&loaded.tree).unwrap(),
+            serde_json::to_value(&snapshot.tree).unwrap()
+        );
+        assert!(matches!(loaded.tree, TreeNode::Directory { total_files: 1, total_size: 42, .. }));
+    }
+
+    #[test]
+    fn formats_timestamps_as_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(951_782_400 + 3661), "2000-02-29 01:01:01 UTC");
+    }
+}
@@ -1,20 +1,50 @@
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::git::DirHistory;
+use crate::lines::LineCounts;
 
 // 修改树结构，将children分为files和dirs
+#[derive(Serialize, Deserialize)]
 pub enum TreeNode {
     File {
         name: String,
         path: PathBuf,
         size: u64,
+        /// 内容哈希（十六进制），仅在启用 --hash 时计算
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hash: Option<String>,
+        /// 行数统计，仅在启用 --lines 时计算
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lines: Option<LineCounts>,
+        /// 根据文件开头字节识别的 MIME 类型，仅在启用 --types 或 --mime 时计算
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mime: Option<String>,
+        /// `git status --porcelain` 的两字符状态码，仅在启用 --git-status 或 --git 时标注，未修改的文件为 None
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        git: Option<String>,
+        /// 管道、套接字等特殊文件：与 ls 一样列出，但不计入文件数和大小，也没有哈希
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        special: bool,
     },
     Directory {
         name: String,
+        path: PathBuf,
         files: Vec<TreeNode>,    // 只存储文件节点
         dirs: Vec<TreeNode>,     // 只存储目录节点
         total_files: usize,      // 包含子目录的总文件数
         total_size: u64,         // 包含子目录的总大小
         direct_files: usize,     // 仅当前目录文件数
         direct_size: u64,        // 仅当前目录文件大小
+        /// 包含子目录的总行数，仅在启用 --lines 时计算
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lines: Option<LineCounts>,
+        /// 压缩包在磁盘上的大小，为 Some 时表示这是 --archives 展开的虚拟目录
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        archive_size: Option<u64>,
+        /// 提交历史统计，仅在启用 --git-history 时计算
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        history: Option<DirHistory>,
     },
 }
 
@@ -24,7 +54,21 @@ impl TreeNode {
         let name = path.file_name()
             .map(|n| n.to_string_lossy().into_owned())
             .unwrap_or_else(|| path.to_string_lossy().into_owned());
-        TreeNode::File { name, path, size }
+        TreeNode::File { name, path, size, hash: None, lines: None, mime: None, git: None, special: false }
+    }
+
+    /// 管道、套接字等特殊文件的节点
+    pub fn new_special(path: PathBuf) -> Self {
+        let mut node = TreeNode::new_file(path, 0);
+        if let TreeNode::File { special, .. } = &mut node {
+            *special = true;
+        }
+        node
+    }
+
+    /// 是否为管道、套接字等特殊文件
+    pub fn is_special(&self) -> bool {
+        matches!(self, TreeNode::File { special: true, .. })
     }
 
     // 将方法改为公有并更新为新结构
@@ -34,12 +78,326 @@ impl TreeNode {
             .unwrap_or_else(|| path.to_string_lossy().into_owned());
         TreeNode::Directory { 
             name, 
+            path,
             files: Vec::new(),
             dirs: Vec::new(),
             total_files: 0,
             total_size: 0,
             direct_files: 0,
             direct_size: 0,
+            lines: None,
+            archive_size: None,
+            history: None,
+        }
+    }
+
+    /// 统计当前节点下（不含自身）的目录数量
+    pub fn count_dirs(&self) -> usize {
+        match self {
+            TreeNode::Directory { dirs, .. } => {
+                dirs.len() + dirs.iter().map(|d| d.count_dirs()).sum::<usize>()
+            }
+            TreeNode::File { .. } => 0,
+        }
+    }
+
+    /// 子树中所有文件节点的可变引用，便于并行处理
+    pub fn files_mut(&mut self) -> Vec<&mut TreeNode> {
+        fn collect<'a>(node: &'a mut TreeNode, out: &mut Vec<&'a mut TreeNode>) {
+            match node {
+                TreeNode::File { .. } => out.push(node),
+                TreeNode::Directory { files, dirs, .. } => {
+                    for child in files.iter_mut().chain(dirs.iter_mut()) {
+                        collect(child, out);
+                    }
+                }
+            }
+        }
+        let mut out = Vec::new();
+        collect(self, &mut out);
+        out
+    }
+
+    /// 节点对应的路径
+    pub fn path(&self) -> &Path {
+        match self {
+            TreeNode::File { path, .. } | TreeNode::Directory { path, .. } => path,
+        }
+    }
+
+    /// 节点名称
+    pub fn name(&self) -> &str {
+        match self {
+            TreeNode::File { name, .. } | TreeNode::Directory { name, .. } => name,
+        }
+    }
+
+    /// 节点的递归大小
+    pub fn total_size(&self) -> u64 {
+        match self {
+            TreeNode::File { size, .. } => *size,
+            TreeNode::Directory { total_size, .. } => *total_size,
+        }
+    }
+
+    /// 节点在磁盘上占用的大小：展开的压缩包为压缩包文件本身的大小，其余与 `total_size` 相同
+    pub fn disk_size(&self) -> u64 {
+        self.archive_size().unwrap_or_else(|| self.total_size())
+    }
+
+    /// 节点的行数统计（目录为包含子目录的总行数）
+    pub fn lines(&self) -> Option<LineCounts> {
+        match self {
+            TreeNode::File { lines, .. } | TreeNode::Directory { lines, .. } => *lines,
+        }
+    }
+
+    /// 压缩包在磁盘上的大小，普通文件和目录为 None
+    pub fn archive_size(&self) -> Option<u64> {
+        match self {
+            TreeNode::Directory { archive_size, .. } => *archive_size,
+            TreeNode::File { .. } => None,
+        }
+    }
+
+    /// 根据直接子节点重新计算当前目录的统计，子目录的统计需已是最新
+    pub fn refresh_totals(&mut self) {
+        if let TreeNode::Directory { files, dirs, total_files, total_size, direct_files, direct_size, lines, .. } = self {
+            let mut line_total: Option<LineCounts> = None;
+            for child_lines in files.iter().chain(dirs.iter()).filter_map(|n| n.lines()) {
+                *line_total.get_or_insert_with(LineCounts::default) += child_lines;
+            }
+            *lines = line_total;
+            *direct_files = files.iter().filter(|f| !f.is_special()).count();
+            *direct_size = files.iter().map(|f| f.total_size()).sum();
+            *total_files = *direct_files;
+            *total_size = *direct_size;
+            for dir in dirs.iter() {
+                if let TreeNode::Directory { total_files: sub_files, .. } = dir {
+                    *total_files += sub_files;
+                    *total_size += dir.disk_size();
+                }
+            }
+        }
+    }
+
+    /// 只保留满足条件的文件，移除变空的子目录，并重新计算统计
+    pub fn retain_files(&mut self, keep: &impl Fn(&TreeNode) -> bool) {
+        if let TreeNode::Directory { files, dirs, .. } = self {
+            files.retain(|file| keep(file));
+            for dir in dirs.iter_mut() {
+                dir.retain_files(keep);
+            }
+            dirs.retain(|dir| matches!(dir, TreeNode::Directory { total_files, .. } if *total_files > 0));
+        }
+        self.refresh_totals();
+    }
+
+    /// 从树中移除指定路径的节点，并沿祖先链更新统计，无需重新扫描
+    pub fn remove_path(&mut self, target: &Path) -> Option<TreeNode> {
+        let TreeNode::Directory { files, dirs, .. } = self else {
+            return None;
+        };
+
+        let removed = if let Some(idx) = files.iter().position(|f| f.path() == target) {
+            Some(files.remove(idx))
+        } else if let Some(idx) = dirs.iter().position(|d| d.path() == target) {
+            Some(dirs.remove(idx))
+        } else {
+            dirs.iter_mut()
+                .find(|d| target.starts_with(d.path()))
+                .and_then(|d| d.remove_path(target))
+        };
+
+        if removed.is_some() {
+            self.refresh_totals();
+        }
+        removed
+    }
+
+    /// 将节点插入到指定路径的目录中，并沿祖先链更新统计；找不到目录时返回 false
+    pub fn insert_into(&mut self, dir_path: &Path, node: TreeNode) -> bool {
+        let TreeNode::Directory { path, files, dirs, .. } = self else {
+            return false;
+        };
+
+        if path.as_path() == dir_path {
+            let list = match node {
+                TreeNode::File { .. } => files,
+                TreeNode::Directory { .. } => dirs,
+            };
+            let pos = list.partition_point(|n| n.name() < node.name());
+            list.insert(pos, node);
+        } else {
+            let inserted = dirs
+                .iter_mut()
+                .find(|d| dir_path.starts_with(d.path()))
+                .is_some_and(|dir| dir.insert_into(dir_path, node));
+            if !inserted {
+                return false;
+            }
+        }
+
+        self.refresh_totals();
+        true
+    }
+
+    /// 节点被移动到新位置后，更新自身及所有子节点的路径
+    pub fn rebase(&mut self, new_path: PathBuf) {
+        match self {
+            TreeNode::File { path, .. } => *path = new_path,
+            TreeNode::Directory { path, files, dirs, .. } => {
+                for child in files.iter_mut().chain(dirs.iter_mut()) {
+                    let child_path = new_path.join(child.name());
+                    child.rebase(child_path);
+                }
+                *path = new_path;
+            }
         }
     }
+
+    /// 用重新扫描的结果替换指定路径的子目录（None 表示该目录已没有文件），
+    /// 缺失的中间目录会被创建，没有文件的目录会被移除，并沿祖先链更新统计
+    pub fn replace_subtree(&mut self, target: &Path, node: Option<TreeNode>) {
+        let TreeNode::Directory { path, dirs, .. } = self else {
+            return;
+        };
+        let Ok(relative) = target.strip_prefix(path.as_path()) else {
+            return;
+        };
+        let Some(first) = relative.components().next() else {
+            return;
+        };
+        let child_path = path.join(first);
+
+        match dirs.iter().position(|d| d.path() == child_path) {
+            Some(idx) if child_path == target => match node {
+                Some(node) => dirs[idx] = node,
+                None => {
+                    dirs.remove(idx);
+                }
+            },
+            Some(idx) => {
+                dirs[idx].replace_subtree(target, node);
+                if let TreeNode::Directory { total_files: 0, .. } = dirs[idx] {
+                    dirs.remove(idx);
+                }
+            }
+            None => {
+                let Some(node) = node else {
+                    return;
+                };
+                let child = if child_path == target {
+                    node
+                } else {
+                    let mut child = TreeNode::new_directory(child_path);
+                    child.replace_subtree(target, Some(node));
+                    child
+                };
+                let pos = dirs.partition_point(|d| d.name() < child.name());
+                dirs.insert(pos, child);
+            }
+        }
+
+        self.refresh_totals();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// root/a.txt (10), root/src/main.rs (100), root/src/util/lib.rs (1000)
+    fn sample() -> TreeNode {
+        let mut root = TreeNode::new_directory("root".into());
+        root.insert_into(Path::new("root"), TreeNode::new_file("root/a.txt".into(), 10));
+        root.insert_into(Path::new("root"), TreeNode::new_directory("root/src".into()));
+        root.insert_into(Path::new("root/src"), TreeNode::new_file("root/src/main.rs".into(), 100));
+        root.insert_into(Path::new("root/src"), TreeNode::new_directory("root/src/util".into()));
+        root.insert_into(Path::new("root/src/util"), TreeNode::new_file("root/src/util/lib.rs".into(), 1000));
+        root
+    }
+
+    /// (总文件数, 总大小, 直接文件数, 直接大小)
+    fn totals(node: &TreeNode) -> (usize, u64, usize, u64) {
+        match node {
+            TreeNode::Directory { total_files, total_size, direct_files, direct_size, .. } => {
+                (*total_files, *total_size, *direct_files, *direct_size)
+            }
+            TreeNode::File { size, .. } => (1, *size, 1, *size),
+        }
+    }
+
+    fn dir<'a>(node: &'a TreeNode, name: &str) -> &'a TreeNode {
+        match node {
+            TreeNode::Directory { dirs, .. } => dirs.iter().find(|d| d.name() == name).unwrap(),
+            TreeNode::File { .. } => panic!("not a directory"),
+        }
+    }
+
+    #[test]
+    fn insert_updates_ancestors() {
+        let root = sample();
+        assert_eq!(totals(&root), (3, 1110, 1, 10));
+        assert_eq!(totals(dir(&root, "src")), (2, 1100, 1, 100));
+        assert_eq!(totals(dir(dir(&root, "src"), "util")), (1, 1000, 1, 1000));
+
+        let mut root = root;
+        assert!(!root.insert_into(Path::new("root/missing"), TreeNode::new_file("root/missing/x".into(), 1)));
+        assert_eq!(totals(&root), (3, 1110, 1, 10));
+    }
+
+    #[test]
+    fn remove_updates_ancestors() {
+        let mut root = sample();
+        let removed = root.remove_path(Path::new("root/src/util/lib.rs")).unwrap();
+        assert_eq!(removed.total_size(), 1000);
+        assert_eq!(totals(&root), (2, 110, 1, 10));
+        assert_eq!(totals(dir(&root, "src")), (1, 100, 1, 100));
+
+        let removed = root.remove_path(Path::new("root/src")).unwrap();
+        assert_eq!(removed.count_dirs(), 1);
+        assert_eq!(totals(&root), (1, 10, 1, 10));
+        assert!(root.remove_path(Path::new("root/src")).is_none());
+    }
+
+    #[test]
+    fn moved_subtree_is_rebased() {
+        let mut root = sample();
+        let mut util = root.remove_path(Path::new("root/src/util")).unwrap();
+        util.rebase("root/util".into());
+        assert!(root.insert_into(Path::new("root"), util));
+
+        assert_eq!(totals(&root), (3, 1110, 1, 10));
+        assert_eq!(totals(dir(&root, "src")), (1, 100, 1, 100));
+        let util = dir(&root, "util");
+        let TreeNode::Directory { files, .. } = util else {
+            panic!("not a directory");
+        };
+        assert_eq!(files[0].path(), Path::new("root/util/lib.rs"));
+    }
+
+    #[test]
+    fn replace_subtree_updates_ancestors() {
+        let mut root = sample();
+
+        // 重新扫描得到的子目录
+        let mut util = TreeNode::new_directory("root/src/util".into());
+        util.insert_into(Path::new("root/src/util"), TreeNode::new_file("root/src/util/lib.rs".into(), 2000));
+        root.replace_subtree(Path::new("root/src/util"), Some(util));
+        assert_eq!(totals(&root), (3, 2110, 1, 10));
+        assert_eq!(totals(dir(&root, "src")), (2, 2100, 1, 100));
+
+        // 缺失的中间目录会被创建
+        let mut deep = TreeNode::new_directory("root/new/deep".into());
+        deep.insert_into(Path::new("root/new/deep"), TreeNode::new_file("root/new/deep/x".into(), 5));
+        root.replace_subtree(Path::new("root/new/deep"), Some(deep));
+        assert_eq!(totals(&root), (4, 2115, 1, 10));
+        assert_eq!(totals(dir(&root, "new")), (1, 5, 0, 0));
+
+        // 没有文件的目录被移除，变空的祖先目录也一起移除
+        root.replace_subtree(Path::new("root/new/deep"), None);
+        assert_eq!(totals(&root), (3, 2110, 1, 10));
+        assert_eq!(root.count_dirs(), 2);
+    }
 }
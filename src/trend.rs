@@ -0,0 +1,225 @@
+use colored::Colorize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::file_size::{format_count, format_size};
+use crate::snapshot::{self, format_timestamp, Snapshot};
+use crate::tree::TreeNode;
+
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// 单个目录在各次快照中的统计和增长速度
+struct DirTrend {
+    path: String,
+    /// 每次快照中的 (文件数, 大小)，目录不存在时为 (0, 0)
+    samples: Vec<(usize, u64)>,
+    size_per_day: f64,
+    files_per_day: f64,
+}
+
+/// 加载快照时被跳过的文件
+pub struct SkippedSnapshot {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// 快照被跳过的原因
+pub enum SkipReason {
+    /// 无法读取或解析
+    Unreadable(io::Error),
+    /// 与最新快照扫描的目录或过滤条件不同
+    DifferentScan,
+}
+
+/// 加载目录中的快照，按扫描时间排序
+///
+/// 只保留与最新快照扫描同一目录、过滤条件相同的快照，其他快照连同原因一起返回，由调用者提示。
+pub fn load_snapshots<P: AsRef<Path>>(dir: P) -> io::Result<(Vec<Snapshot>, Vec<SkippedSnapshot>)> {
+    let mut snapshots = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        match snapshot::load(&path) {
+            Ok(snapshot) => snapshots.push((path, snapshot)),
+            Err(err) => skipped.push(SkippedSnapshot { path, reason: SkipReason::Unreadable(err) }),
+        }
+    }
+    snapshots.sort_by_key(|(_, s)| s.metadata.timestamp);
+
+    let Some((_, latest)) = snapshots.pop() else {
+        return Ok((Vec::new(), skipped));
+    };
+    let mut matching = Vec::new();
+    for (path, snapshot) in snapshots {
+        if snapshot.metadata.same_scan(&latest.metadata) {
+            matching.push(snapshot);
+        } else {
+            skipped.push(SkippedSnapshot { path, reason: SkipReason::DifferentScan });
+        }
+    }
+    matching.push(latest);
+    Ok((matching, skipped))
+}
+
+/// 收集树中每个目录（以相对根目录的路径为键）的递归统计
+fn collect_dirs(node: &TreeNode, rel_path: &str, out: &mut HashMap<String, (usize, u64)>) {
+    if let TreeNode::Directory { dirs, total_files, total_size, .. } = node {
+        out.insert(rel_path.to_string(), (*total_files, *total_size));
+        for dir in dirs {
+            let child_path = if rel_path == "." {
+                dir.name().to_string()
+            } else {
+                format!("{}/{}", rel_path, dir.name())
+            };
+            collect_dirs(dir, &child_path, out);
+        }
+    }
+}
+
+/// 最小二乘法求斜率（每天的变化量）
+fn slope_per_day(times: &[u64], values: &[f64]) -> f64 {
+    let n = times.len() as f64;
+    let days: Vec<f64> = times.iter().map(|&t| t as f64 / SECONDS_PER_DAY).collect();
+    let mean_x = days.iter().sum::<f64>() / n;
+    let mean_y = values.iter().sum::<f64>() / n;
+    let (mut num, mut den) = (0.0, 0.0);
+    for (x, y) in days.iter().zip(values) {
+        num += (x - mean_x) * (y - mean_y);
+        den += (x - mean_x) * (x - mean_x);
+    }
+    if den == 0.0 {
+        0.0
+    } else {
+        num / den
+    }
+}
+
+/// 用方块字符绘制迷你折线图
+fn sparkline(values: &[u64]) -> String {
+    const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().copied().min().unwrap_or(0);
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&v| {
+            if max == min {
+                TICKS[0]
+            } else {
+                TICKS[((v - min) as f64 / (max - min) as f64 * 7.0).round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// 带符号的每日增长量，例如 `+1.20 MB/day`
+fn format_rate(bytes_per_day: f64) -> String {
+    let sign = if bytes_per_day < 0.0 { "-" } else { "+" };
+    format!("{}{}/day", sign, format_size(bytes_per_day.abs().round() as u64))
+}
+
+/// 将增长最快的目录写入 `out`
+pub fn write_trend(snapshots: &[Snapshot], top: usize, out: &mut dyn Write) -> io::Result<()> {
+    if snapshots.len() < 2 {
+        return writeln!(out, "At least two snapshots are needed to compute a trend");
+    }
+
+    let first = &snapshots[0].metadata;
+    let last = &snapshots[snapshots.len() - 1].metadata;
+    writeln!(
+        out,
+        "{} snapshots of {} from {} to {}",
+        snapshots.len(),
+        first.root.blue(),
+        format_timestamp(first.timestamp),
+        format_timestamp(last.timestamp)
+    )?;
+    if first.timestamp == last.timestamp {
+        return writeln!(out, "All snapshots have the same timestamp, no trend can be computed");
+    }
+
+    // 每次快照中各目录的统计
+    let per_snapshot: Vec<HashMap<String, (usize, u64)>> = snapshots
+        .iter()
+        .map(|s| {
+            let mut dirs = HashMap::new();
+            collect_dirs(&s.tree, ".", &mut dirs);
+            dirs
+        })
+        .collect();
+    let times: Vec<u64> = snapshots.iter().map(|s| s.metadata.timestamp).collect();
+
+    let mut all_paths: Vec<&String> = per_snapshot.iter().flat_map(|dirs| dirs.keys()).collect();
+    all_paths.sort();
+    all_paths.dedup();
+
+    let mut trends: Vec<DirTrend> = all_paths
+        .into_iter()
+        .map(|path| {
+            let samples: Vec<(usize, u64)> = per_snapshot
+                .iter()
+                .map(|dirs| dirs.get(path).copied().unwrap_or((0, 0)))
+                .collect();
+            let sizes: Vec<f64> = samples.iter().map(|(_, size)| *size as f64).collect();
+            let files: Vec<f64> = samples.iter().map(|(files, _)| *files as f64).collect();
+            DirTrend {
+                path: path.clone(),
+                size_per_day: slope_per_day(&times, &sizes),
+                files_per_day: slope_per_day(&times, &files),
+                samples,
+            }
+        })
+        .collect();
+
+    trends.sort_by(|a, b| b.size_per_day.total_cmp(&a.size_per_day));
+    if top > 0 {
+        trends.truncate(top);
+    }
+
+    writeln!(out, "\n{}", "Fastest-growing directories:".yellow().bold())?;
+    for trend in &trends {
+        let sizes: Vec<u64> = trend.samples.iter().map(|(_, size)| *size).collect();
+        let (first_files, first_size) = trend.samples[0];
+        let (last_files, last_size) = trend.samples[trend.samples.len() - 1];
+        let rate = format_rate(trend.size_per_day);
+        let rate = if trend.size_per_day < 0.0 { rate.red() } else { rate.green() };
+        writeln!(
+            out,
+            "{:>16}  {:>+9.1} files/day  {}  {} → {} ({} → {} files)  {}",
+            rate,
+            trend.files_per_day,
+            sparkline(&sizes).magenta(),
+            format_size(first_size),
+            format_size(last_size),
+            format_count(first_files),
+            format_count(last_files),
+            trend.path.blue().bold()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+
+    #[test]
+    fn slope_is_change_per_day() {
+        let times = [1_700_000_000, 1_700_000_000 + DAY, 1_700_000_000 + 3 * DAY];
+        assert_eq!(slope_per_day(&times, &[100.0, 200.0, 400.0]), 100.0);
+        assert_eq!(slope_per_day(&times, &[50.0, 50.0, 50.0]), 0.0);
+        assert_eq!(slope_per_day(&times[..2], &[10.0, 4.0]), -6.0);
+    }
+
+    #[test]
+    fn slope_of_simultaneous_snapshots_is_zero() {
+        assert_eq!(slope_per_day(&[DAY, DAY], &[1.0, 5.0]), 0.0);
+        assert_eq!(slope_per_day(&[DAY], &[1.0]), 0.0);
+    }
+}
@@ -0,0 +1,347 @@
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::archive;
+use crate::list_files::{build_directory_tree, file_nodes, process_directory_entries};
+use crate::scanner::ScanOptions;
+use crate::tree::TreeNode;
+
+/// 合并短时间内连续发生的事件
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// 一批事件最多合并的时长，持续写入时也按这个间隔刷新
+const MAX_BATCH: Duration = Duration::from_secs(1);
+
+/// 文件系统变化的类型
+pub enum ChangeKind {
+    Created,
+    Deleted,
+    Modified,
+}
+
+/// 一次文件系统变化
+pub struct ChangeEvent {
+    pub kind: ChangeKind,
+    pub path: PathBuf,
+    /// 事件发生的 Unix 时间戳（秒）
+    pub timestamp: u64,
+}
+
+impl ChangeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+/// 监听模式的状态：inotify 句柄和监听描述符对应的目录
+struct Watcher<'a> {
+    inotify: Inotify,
+    watches: HashMap<WatchDescriptor, PathBuf>,
+    root: PathBuf,
+    options: &'a ScanOptions,
+}
+
+impl Watcher<'_> {
+    /// 目录相对根目录的深度，根目录为1
+    fn depth(&self, dir: &Path) -> usize {
+        dir.strip_prefix(&self.root)
+            .map(|rel| rel.components().count() + 1)
+            .unwrap_or(1)
+    }
+
+    /// 递归监听目录，与扫描时一样跳过隐藏和被忽略的目录
+    fn watch_recursive(&mut self, dir: &Path) {
+        let depth = self.depth(dir);
+        if self.options.beyond_depth(depth) {
+            return;
+        }
+
+        let mask = WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MODIFY
+            | WatchMask::CLOSE_WRITE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO;
+        match self.inotify.watches().add(dir, mask) {
+            Ok(wd) => {
+                self.watches.insert(wd, dir.to_path_buf());
+            }
+            Err(err) => {
+                self.options.report_error(dir, &err);
+                return;
+            }
+        }
+
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if self.options.skips(name) {
+                continue;
+            }
+            if path.is_dir() {
+                self.watch_recursive(&path);
+            }
+        }
+    }
+
+    /// 只重新读取目录的直接条目，已有的子目录节点直接复用
+    fn rescan_dir(&self, tree: &mut TreeNode, dir: &Path) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(Result::ok).collect(),
+            Err(_) => {
+                // 目录已被删除
+                tree.replace_subtree(dir, None);
+                return;
+            }
+        };
+        let (files, special, dirs) = process_directory_entries(entries, self.options);
+        let (files, archive_nodes) = if self.options.archives {
+            archive::expand_archives(files, self.options, self.depth(dir) + 1)
+        } else {
+            (files, Vec::new())
+        };
+
+        // 取出原有的子目录节点以便复用
+        let mut old_dirs: HashMap<PathBuf, TreeNode> = HashMap::new();
+        let existing = if dir == tree.path() {
+            Some(&mut *tree)
+        } else {
+            find_dir_mut(tree, dir)
+        };
+        if let Some(TreeNode::Directory { dirs, .. }) = existing {
+            for node in dirs.drain(..) {
+                old_dirs.insert(node.path().to_path_buf(), node);
+            }
+        }
+
+        let mut node = TreeNode::new_directory(dir.to_path_buf());
+        if let TreeNode::Directory { files: node_files, dirs: node_dirs, .. } = &mut node {
+            *node_files = file_nodes(files, special);
+            for subdir in dirs {
+                let child = old_dirs.remove(&subdir).or_else(|| {
+                    build_directory_tree(&subdir, self.options, self.depth(&subdir))
+                });
+                node_dirs.extend(child);
+            }
+            node_dirs.extend(archive_nodes);
+            node_dirs.sort_by(|a, b| a.name().cmp(b.name()));
+        }
+        node.refresh_totals();
+
+        if dir == tree.path() {
+            *tree = node;
+        } else {
+            let has_files = matches!(node, TreeNode::Directory { total_files, .. } if total_files > 0);
+            tree.replace_subtree(dir, has_files.then_some(node));
+        }
+    }
+}
+
+/// 在树中查找指定路径的目录节点
+fn find_dir_mut<'a>(node: &'a mut TreeNode, target: &Path) -> Option<&'a mut TreeNode> {
+    if node.path() == target {
+        return Some(node);
+    }
+    match node {
+        TreeNode::Directory { dirs, .. } => dirs
+            .iter_mut()
+            .find(|d| target.starts_with(d.path()))
+            .and_then(|d| find_dir_mut(d, target)),
+        TreeNode::File { .. } => None,
+    }
+}
+
+/// 树中是否有指定路径的文件或目录
+fn contains_path(node: &TreeNode, target: &Path) -> bool {
+    if node.path() == target {
+        return true;
+    }
+    match node {
+        TreeNode::Directory { files, dirs, .. } if target.starts_with(node.path()) => {
+            files.iter().chain(dirs.iter()).any(|child| contains_path(child, target))
+        }
+        _ => false,
+    }
+}
+
+/// 合并同一路径的连续事件，按第一次出现的顺序输出
+///
+/// 新建后修改仍为新建，新建后删除的临时文件不报告，删除后重新创建视为修改。
+fn merge_events(events: Vec<ChangeEvent>) -> Vec<ChangeEvent> {
+    let mut merged: Vec<Option<ChangeEvent>> = Vec::new();
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+    for event in events {
+        let Some(&idx) = index.get(&event.path) else {
+            index.insert(event.path.clone(), merged.len());
+            merged.push(Some(event));
+            continue;
+        };
+        let kind = match (merged[idx].as_ref().map(|e| &e.kind), &event.kind) {
+            (None, _) => Some(event.kind),
+            (Some(ChangeKind::Created), ChangeKind::Deleted) => None,
+            (Some(ChangeKind::Created), _) => Some(ChangeKind::Created),
+            (Some(ChangeKind::Deleted), ChangeKind::Created) => Some(ChangeKind::Modified),
+            (Some(_), _) => Some(event.kind),
+        };
+        merged[idx] = kind.map(|kind| ChangeEvent { kind, ..event });
+    }
+    merged.into_iter().flatten().collect()
+}
+
+/// 初始扫描后持续监听目录变化，更新内存中的树并通过回调通知
+pub fn watch(
+    mut tree: TreeNode,
+    options: &ScanOptions,
+    mut on_change: impl FnMut(&TreeNode, &[ChangeEvent]),
+) -> io::Result<()> {
+    let root = tree.path().to_path_buf();
+    let mut watcher = Watcher {
+        inotify: Inotify::init()?,
+        watches: HashMap::new(),
+        root: root.clone(),
+        options,
+    };
+    watcher.watch_recursive(&root);
+    on_change(&tree, &[]);
+
+    let mut buffer = [0; 4096];
+    loop {
+        let mut changed_dirs = BTreeSet::new();
+        let mut new_dirs = Vec::new();
+        let mut events = Vec::new();
+
+        // 阻塞等待第一个事件，然后短暂等待以合并后续事件；
+        // 从第一个事件起最多合并 MAX_BATCH，剩余事件留到下一批
+        let mut first = true;
+        let mut deadline = None;
+        loop {
+            let read = if first {
+                watcher.inotify.read_events_blocking(&mut buffer)
+            } else {
+                watcher.inotify.read_events(&mut buffer)
+            };
+            let batch = match read {
+                Ok(batch) => batch,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            };
+
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            for event in batch {
+                let Some(dir) = watcher.watches.get(&event.wd) else {
+                    continue;
+                };
+                let Some(name) = event.name else {
+                    continue;
+                };
+                // 与扫描时一样跳过隐藏和被忽略的条目
+                if options.skips(&name.to_string_lossy()) {
+                    continue;
+                }
+                let path = dir.join(name);
+                let kind = if event.mask.intersects(EventMask::CREATE | EventMask::MOVED_TO) {
+                    ChangeKind::Created
+                } else if event.mask.intersects(EventMask::DELETE | EventMask::MOVED_FROM) {
+                    ChangeKind::Deleted
+                } else {
+                    ChangeKind::Modified
+                };
+                if event.mask.contains(EventMask::ISDIR) && matches!(kind, ChangeKind::Created) {
+                    new_dirs.push(path.clone());
+                }
+                changed_dirs.insert(dir.clone());
+                events.push(ChangeEvent { kind, path, timestamp });
+            }
+
+            first = false;
+            let deadline = *deadline.get_or_insert_with(|| Instant::now() + MAX_BATCH);
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            thread::sleep(DEBOUNCE.min(remaining));
+        }
+
+        // 被删除的条目只有在扫描结果中出现过时才报告
+        let mut events = merge_events(events);
+        events.retain(|event| !matches!(event.kind, ChangeKind::Deleted) || contains_path(&tree, &event.path));
+
+        // 新建的目录也需要监听
+        for dir in new_dirs {
+            watcher.watch_recursive(&dir);
+        }
+        for dir in &changed_dirs {
+            watcher.rescan_dir(&mut tree, dir);
+        }
+
+        // 新建和修改的条目需满足过滤条件，即出现在重新扫描的结果中
+        events.retain(|event| matches!(event.kind, ChangeKind::Deleted) || contains_path(&tree, &event.path));
+        if !events.is_empty() {
+            on_change(&tree, &events);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: ChangeKind, path: &str) -> ChangeEvent {
+        ChangeEvent { kind, path: PathBuf::from(path), timestamp: 0 }
+    }
+
+    fn labels(events: &[ChangeEvent]) -> Vec<(&'static str, &Path)> {
+        events.iter().map(|e| (e.kind.label(), e.path.as_path())).collect()
+    }
+
+    #[test]
+    fn merges_events_per_path() {
+        let merged = merge_events(vec![
+            event(ChangeKind::Created, "new.rs"),
+            event(ChangeKind::Modified, "old.rs"),
+            event(ChangeKind::Modified, "new.rs"),
+            event(ChangeKind::Created, "tmp.rs"),
+            event(ChangeKind::Deleted, "tmp.rs"),
+            event(ChangeKind::Deleted, "old.rs"),
+            event(ChangeKind::Deleted, "swap.rs"),
+            event(ChangeKind::Created, "swap.rs"),
+        ]);
+        assert_eq!(
+            labels(&merged),
+            [
+                ("created", Path::new("new.rs")),
+                ("deleted", Path::new("old.rs")),
+                ("modified", Path::new("swap.rs")),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_paths_in_tree() {
+        let mut tree = TreeNode::new_directory("root".into());
+        tree.insert_into(Path::new("root"), TreeNode::new_directory("root/src".into()));
+        tree.insert_into(Path::new("root/src"), TreeNode::new_file("root/src/main.rs".into(), 1));
+        assert!(contains_path(&tree, Path::new("root/src/main.rs")));
+        assert!(contains_path(&tree, Path::new("root/src")));
+        assert!(!contains_path(&tree, Path::new("root/src/lib.rs")));
+        assert!(!contains_path(&tree, Path::new("other/src/main.rs")));
+    }
+}